@@ -0,0 +1,56 @@
+// Copyright 2019 Liebi Technologies.
+// This file is part of Bifrost.
+
+// Bifrost is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Bifrost is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Bifrost.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Prints the differences between two runtime metadata versions.
+//!
+//!     metadata-diff <old> <new>
+//!
+//! Each argument is either a node's websocket url (`ws://127.0.0.1:9944`) or a file holding
+//! the metadata as accepted by `node_metadata::load_metadata`.
+
+use primitives::sr25519;
+use std::{env, process};
+use substrate_rpc_client::{
+    metadata_diff::diff_metadata,
+    node_metadata::{load_metadata, NodeMetadata},
+    Api,
+};
+
+fn fetch(source: &str) -> NodeMetadata {
+    if source.starts_with("ws://") || source.starts_with("wss://") {
+        Api::<sr25519::Pair>::new(source.to_string()).metadata
+    } else {
+        load_metadata(source).unwrap_or_else(|e| {
+            eprintln!("could not load metadata from {}: {}", source, e);
+            process::exit(2);
+        })
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        eprintln!("usage: {} <old url|file> <new url|file>", args[0]);
+        process::exit(2);
+    }
+
+    let diff = diff_metadata(&fetch(&args[1]), &fetch(&args[2]));
+    print!("{}", diff);
+    if !diff.is_empty() {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,269 @@
+// Copyright 2019 Liebi Technologies.
+// This file is part of Bifrost.
+
+// Bifrost is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Bifrost is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Bifrost.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Typed headers and blocks as returned by `chain_getHeader` and `chain_getBlock`.
+
+use crate::extrinsic::xt_primitives::{GenericCall, UncheckedExtrinsicV4};
+use crate::node_metadata::{call_modules, NodeMetadata};
+use crate::utils::hexstr_to_vec;
+use codec::Decode;
+use primitives::H256 as Hash;
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Header {
+    pub parent_hash: Hash,
+    #[serde(deserialize_with = "deserialize_number")]
+    pub number: u32,
+    pub state_root: Hash,
+    pub extrinsics_root: Hash,
+    pub digest: Digest,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Digest {
+    /// The hex encoded digest items.
+    pub logs: Vec<String>,
+}
+
+/// An extrinsic of a block together with the names of its module and call, if the metadata
+/// knows them.
+#[derive(Debug, Clone)]
+pub struct BlockExtrinsic {
+    pub module: Option<String>,
+    pub call: Option<String>,
+    pub extrinsic: UncheckedExtrinsicV4<GenericCall>,
+}
+
+/// An extrinsic of a block that couldn't be decoded, e.g. because of custom signed extensions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawExtrinsic {
+    /// The hex encoded extrinsic as returned by the node.
+    pub hex: String,
+    /// Why it couldn't be decoded.
+    pub error: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub header: Header,
+    /// The extrinsics in block order. Those that couldn't be decoded are kept raw.
+    pub extrinsics: Vec<Result<BlockExtrinsic, RawExtrinsic>>,
+}
+
+/// The json of a block as returned by `chain_getBlock`, before decoding the extrinsics.
+#[derive(Deserialize, Debug)]
+pub struct SignedBlockJson {
+    pub block: BlockJson,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BlockJson {
+    pub header: Header,
+    /// The hex encoded extrinsics.
+    pub extrinsics: Vec<String>,
+}
+
+impl BlockJson {
+    /// Decodes the extrinsics and resolves their call names from `metadata`. Extrinsics that
+    /// fail to decode don't fail the block, they are kept as `RawExtrinsic`.
+    pub fn decode(self, metadata: &NodeMetadata) -> Block {
+        Block { header: self.header, extrinsics: decode_extrinsics(metadata, self.extrinsics) }
+    }
+}
+
+/// Decodes hex encoded extrinsics with `decode_extrinsic`, keeping those that fail to decode
+/// as `RawExtrinsic`.
+pub fn decode_extrinsics(metadata: &NodeMetadata, hexstrs: Vec<String>) -> Vec<Result<BlockExtrinsic, RawExtrinsic>> {
+    hexstrs
+        .into_iter()
+        .map(|xt| decode_extrinsic(metadata, xt.clone()).map_err(|e| RawExtrinsic { hex: xt, error: format!("{:?}", e) }))
+        .collect()
+}
+
+/// Decodes a hex encoded extrinsic and resolves its call name from `metadata`.
+pub fn decode_extrinsic(metadata: &NodeMetadata, hexstr: String) -> Result<BlockExtrinsic, codec::Error> {
+    let bytes = hexstr_to_vec(hexstr).map_err(|_| codec::Error::from("Invalid hex"))?;
+    let extrinsic = UncheckedExtrinsicV4::<GenericCall>::decode(&mut bytes.as_slice())?;
+
+    let module = call_modules(metadata).nth(extrinsic.function.module_index as usize);
+    let call = module
+        .and_then(|m| m.calls.get(extrinsic.function.call_index as usize))
+        .map(|c| c.name.clone());
+    Ok(BlockExtrinsic { module: module.map(|m| m.name.clone()), call, extrinsic })
+}
+
+/// Returns the headers of the blocks after block number `last` up to and including `header`,
+/// in ascending order. Missing headers are looked up by parent hash with `fetch_header`, so
+/// this fills the gap when notifications skip blocks.
+pub fn fill_gap<E, F>(last: u32, header: Header, mut fetch_header: F) -> Result<Vec<Header>, E>
+where
+    F: FnMut(Hash) -> Result<Header, E>,
+{
+    let mut headers = vec![header];
+    while headers[headers.len() - 1].number > last + 1 {
+        let parent = fetch_header(headers[headers.len() - 1].parent_hash)?;
+        headers.push(parent);
+    }
+    headers.reverse();
+    Ok(headers)
+}
+
+/// Block numbers are serialized as hex strings like `"0x1a"`.
+fn deserialize_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    let number = String::deserialize(deserializer)?;
+    u32::from_str_radix(number.trim_start_matches("0x"), 16).map_err(de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extrinsic::xt_primitives::{GenericAddress, GenericExtra};
+    use crate::node_metadata::{Call, Module};
+    use codec::Encode;
+    use node_primitives::AccountId;
+    use primitives::sr25519;
+    use runtime_primitives::MultiSignature;
+
+    fn metadata() -> NodeMetadata {
+        let call = |name: &str| Call { name: name.to_string(), args: vec![] };
+        vec![
+            Module { name: "System".to_string(), calls: vec![call("remark")], ..Default::default() },
+            Module { name: "Indices".to_string(), ..Default::default() },
+            Module {
+                name: "Balances".to_string(),
+                calls: vec![call("transfer"), call("set_balance")],
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn test_decode_header() {
+        let header: Header = serde_json::from_str(
+            r#"{
+                "parentHash": "0x0101010101010101010101010101010101010101010101010101010101010101",
+                "number": "0x1a",
+                "stateRoot": "0x0202020202020202020202020202020202020202020202020202020202020202",
+                "extrinsicsRoot": "0x0303030303030303030303030303030303030303030303030303030303030303",
+                "digest": { "logs": ["0x0600"] }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(header.number, 26);
+        assert_eq!(header.parent_hash, Hash::repeat_byte(1));
+        assert_eq!(header.state_root, Hash::repeat_byte(2));
+        assert_eq!(header.digest.logs, vec!["0x0600".to_string()]);
+    }
+
+    #[test]
+    fn test_decode_extrinsics() {
+        let call = GenericCall { module_index: 1, call_index: 0, args: (7u32, 9u128).encode() };
+        let signed = UncheckedExtrinsicV4::new_signed(
+            call.clone(),
+            GenericAddress::from(AccountId::from([1u8; 32])),
+            MultiSignature::from(sr25519::Signature::default()),
+            GenericExtra::new(3),
+        );
+        let unsigned = |module_index| UncheckedExtrinsicV4 {
+            signature: None,
+            function: GenericCall { module_index, call_index: 0, args: vec![] },
+        };
+        let names = |xt: &BlockExtrinsic| (xt.module.clone(), xt.call.clone());
+
+        let xt = decode_extrinsic(&metadata(), signed.hex_encode()).unwrap();
+        assert_eq!(names(&xt), (Some("Balances".to_string()), Some("transfer".to_string())));
+        assert_eq!(xt.extrinsic.function, call);
+        assert_eq!(xt.extrinsic.signature.map(|s| s.2), Some(GenericExtra::new(3)));
+        assert_eq!(xt.extrinsic.encode(), signed.encode());
+
+        let xt = decode_extrinsic(&metadata(), unsigned(0).hex_encode()).unwrap();
+        assert_eq!(names(&xt), (Some("System".to_string()), Some("remark".to_string())));
+        assert!(xt.extrinsic.signature.is_none());
+
+        // module indices beyond the modules with calls are unknown
+        let xt = decode_extrinsic(&metadata(), unsigned(5).hex_encode()).unwrap();
+        assert_eq!(names(&xt), (None, None));
+    }
+
+    #[test]
+    fn test_decode_extrinsic_bounded_by_length() {
+        let xt = UncheckedExtrinsicV4 {
+            signature: None,
+            function: GenericCall { module_index: 0, call_index: 0, args: vec![1, 2] },
+        };
+        // the call stops at the length prefix instead of taking the following bytes
+        let mut bytes = xt.encode();
+        bytes.extend_from_slice(&[3, 4]);
+        let mut input = bytes.as_slice();
+        let decoded = UncheckedExtrinsicV4::<GenericCall>::decode(&mut input).unwrap();
+        assert_eq!(decoded.function.args, vec![1, 2]);
+        assert_eq!(input, &[3, 4]);
+
+        // a length prefix beyond the input is rejected before allocating
+        let mut bytes = codec::Compact(u32::max_value()).encode();
+        bytes.extend_from_slice(&[4, 0, 0]);
+        assert!(UncheckedExtrinsicV4::<GenericCall>::decode(&mut bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_decode_block_keeps_undecodable_extrinsics() {
+        let unsigned = UncheckedExtrinsicV4 {
+            signature: None,
+            function: GenericCall { module_index: 0, call_index: 0, args: vec![] },
+        };
+        let block: SignedBlockJson = serde_json::from_value(serde_json::json!({
+            "block": {
+                "header": {
+                    "parentHash": Hash::default(),
+                    "number": "0x1",
+                    "stateRoot": Hash::default(),
+                    "extrinsicsRoot": Hash::default(),
+                    "digest": { "logs": [] }
+                },
+                "extrinsics": [unsigned.hex_encode(), "0x0805"]
+            }
+        }))
+        .unwrap();
+
+        let block = block.block.decode(&metadata());
+        assert_eq!(block.extrinsics.len(), 2);
+        assert_eq!(block.extrinsics[0].as_ref().unwrap().call, Some("remark".to_string()));
+        assert_eq!(block.extrinsics[1].as_ref().unwrap_err().hex, "0x0805");
+    }
+
+    #[test]
+    fn test_fill_gap() {
+        let header = |number: u32| Header {
+            parent_hash: Hash::repeat_byte(number as u8 - 1),
+            number,
+            state_root: Hash::default(),
+            extrinsics_root: Hash::default(),
+            digest: Digest { logs: vec![] },
+        };
+        let fetch = |hash: Hash| match hash.as_bytes()[0] {
+            2 => Err("unknown block"),
+            n => Ok(header(n as u32)),
+        };
+
+        let numbers = |headers: Vec<Header>| headers.iter().map(|h| h.number).collect::<Vec<_>>();
+        assert_eq!(fill_gap(2, header(6), fetch).map(numbers), Ok(vec![3, 4, 5, 6]));
+        assert_eq!(fill_gap(5, header(6), fetch).map(numbers), Ok(vec![6]));
+        assert_eq!(fill_gap(6, header(6), fetch).map(numbers), Ok(vec![6]));
+        assert_eq!(fill_gap(0, header(3), fetch).map(numbers), Err("unknown block"));
+    }
+}
@@ -0,0 +1,414 @@
+// Copyright 2019 Liebi Technologies.
+// This file is part of Bifrost.
+
+// Bifrost is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Bifrost is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Bifrost.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Generates typed Rust APIs from a metadata snapshot. For every module with calls, events or
+//! storage a Rust module is emitted containing
+//!
+//! * `calls`: one constructor per call, returning the same tuple as `compose_call!`, ready to be
+//!   passed to `compose_extrinsic_offline!` and sent as `UncheckedExtrinsicV4`.
+//! * `events`: an `Event` enum with one variant per event, decodable from the event bytes
+//!   following the module index.
+//! * `storage`: one accessor per storage item, reading it via the typed `Api::get_storage_*` reads.
+//!
+//! Usage from a build script:
+//!
+//! ```ignore
+//! substrate_rpc_client::codegen::generate_from_file(
+//!     "metadata/bifrost.hex",
+//!     Path::new(&env::var("OUT_DIR").unwrap()).join("runtime.rs"),
+//! ).unwrap();
+//! ```
+//!
+//! and `include!(concat!(env!("OUT_DIR"), "/runtime.rs"));` in the crate. Types that are not
+//! known to this crate (e.g. `ProducerSchedule`) are resolved in the including module's scope.
+
+use crate::node_metadata::{
+    call_module_index, event_module_index, load_metadata, normalize_type, Module, NodeMetadata,
+    StorageType,
+};
+use std::{error::Error, fmt::Write, fs, path::Path};
+
+const CRATE: &str = "substrate_rpc_client";
+
+/// Reads the metadata from `metadata_path` (see `load_metadata`) and writes the generated
+/// code to `out_path`.
+pub fn generate_from_file<M: AsRef<Path>, O: AsRef<Path>>(
+    metadata_path: M,
+    out_path: O,
+) -> Result<(), Box<dyn Error>> {
+    let metadata = load_metadata(metadata_path)?;
+    fs::write(out_path, generate(&metadata))?;
+    Ok(())
+}
+
+/// Generates the Rust source for all modules of the metadata.
+pub fn generate(metadata: &NodeMetadata) -> String {
+    let mut out = String::new();
+    writeln!(out, "// Generated by {}::codegen. Do not edit.", CRATE).unwrap();
+    for module in metadata {
+        if module.calls.is_empty() && module.events.is_empty() && module.storage.is_empty() {
+            continue;
+        }
+        writeln!(out).unwrap();
+        writeln!(out, "#[allow(dead_code, non_camel_case_types)]").unwrap();
+        writeln!(out, "pub mod {} {{", snake_case(&module.name)).unwrap();
+        generate_calls(metadata, module, &mut out);
+        generate_events(metadata, module, &mut out);
+        generate_storage(module, &mut out);
+        writeln!(out, "}}").unwrap();
+    }
+    out
+}
+
+fn imports(out: &mut String) {
+    writeln!(out, "        #[allow(unused_imports)]").unwrap();
+    writeln!(out, "        use super::super::*;").unwrap();
+    writeln!(out, "        #[allow(unused_imports)]").unwrap();
+    writeln!(out, "        use {}::extrinsic::codec::{{Compact, Decode, Encode, Error, Input}};", CRATE).unwrap();
+    writeln!(out, "        #[allow(unused_imports)]").unwrap();
+    writeln!(
+        out,
+        "        use {}::extrinsic::node_primitives::{{AccountId, AccountIndex, Balance, BlockNumber, Hash, Index, Moment}};",
+        CRATE
+    )
+    .unwrap();
+    writeln!(out, "        #[allow(unused_imports)]").unwrap();
+    writeln!(out, "        use {}::extrinsic::xt_primitives::GenericAddress;", CRATE).unwrap();
+}
+
+fn generate_calls(metadata: &NodeMetadata, module: &Module, out: &mut String) {
+    let module_index = match call_module_index(metadata, &module.name) {
+        Some(i) => i,
+        None => return,
+    };
+    writeln!(out, "    pub mod calls {{").unwrap();
+    imports(out);
+    writeln!(out).unwrap();
+    writeln!(out, "        pub const MODULE_INDEX: u8 = {};", module_index).unwrap();
+
+    for (call_index, call) in module.calls.iter().enumerate() {
+        // placeholder of modules listing an empty call enum
+        if call.name.is_empty() {
+            continue;
+        }
+        let mut generics = Vec::new();
+        let mut params = Vec::new();
+        let mut types = Vec::new();
+        for arg in &call.args {
+            let ty = match rust_type(&arg.ty) {
+                Some(ty) => ty,
+                None => {
+                    // nested calls, e.g. the proposal of `Sudo::sudo`, can be any encodable call
+                    let generic = format!("Call{}", generics.len());
+                    generics.push(format!("{}: Encode", generic));
+                    generic
+                }
+            };
+            params.push(format!("{}: {}", ident(&arg.name), ty));
+            types.push(ty);
+        }
+
+        let generics = if generics.is_empty() {
+            String::new()
+        } else {
+            format!("<{}>", generics.join(", "))
+        };
+        let (ret, body) = if types.is_empty() {
+            ("[u8; 2]".to_string(), format!("[MODULE_INDEX, {}]", call_index))
+        } else {
+            let names = call.args.iter().map(|a| ident(&a.name)).collect::<Vec<_>>();
+            (
+                format!("([u8; 2], {})", types.join(", ")),
+                format!("([MODULE_INDEX, {}], {})", call_index, names.join(", ")),
+            )
+        };
+
+        writeln!(out).unwrap();
+        writeln!(out, "        pub fn {}{}({}) -> {} {{", ident(&call.name), generics, params.join(", "), ret).unwrap();
+        writeln!(out, "            {}", body).unwrap();
+        writeln!(out, "        }}").unwrap();
+    }
+    writeln!(out, "    }}").unwrap();
+}
+
+fn generate_events(metadata: &NodeMetadata, module: &Module, out: &mut String) {
+    let module_index = match event_module_index(metadata, &module.name) {
+        Some(i) => i,
+        None => return,
+    };
+    writeln!(out, "    pub mod events {{").unwrap();
+    imports(out);
+    writeln!(out).unwrap();
+    writeln!(out, "        pub const MODULE_INDEX: u8 = {};", module_index).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "        #[derive(Clone, Debug)]").unwrap();
+    writeln!(out, "        pub enum Event {{").unwrap();
+    for event in &module.events {
+        match event.args.iter().map(|a| rust_type(a)).collect::<Option<Vec<_>>>() {
+            // nested calls have no fixed type and no length prefix, so they can't be decoded
+            None => writeln!(out, "            // {}: has a nested call argument, not decodable", event.name).unwrap(),
+            Some(ref args) if args.is_empty() => writeln!(out, "            {},", event.name).unwrap(),
+            Some(args) => writeln!(out, "            {}({}),", event.name, args.join(", ")).unwrap(),
+        }
+    }
+    writeln!(out, "        }}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "        impl Decode for Event {{").unwrap();
+    writeln!(out, "            fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {{").unwrap();
+    writeln!(out, "                match input.read_byte()? {{").unwrap();
+    for (index, event) in module.events.iter().enumerate() {
+        if event.args.iter().any(|a| rust_type(a).is_none()) {
+            writeln!(
+                out,
+                "                    {} => Err(\"{}::{} has a nested call argument\".into()),",
+                index, module.name, event.name
+            )
+            .unwrap();
+        } else if event.args.is_empty() {
+            writeln!(out, "                    {} => Ok(Event::{}),", index, event.name).unwrap();
+        } else {
+            let fields = vec!["Decode::decode(input)?"; event.args.len()];
+            writeln!(out, "                    {} => Ok(Event::{}({})),", index, event.name, fields.join(", ")).unwrap();
+        }
+    }
+    writeln!(out, "                    _ => Err(\"unknown event index\".into()),").unwrap();
+    writeln!(out, "                }}").unwrap();
+    writeln!(out, "            }}").unwrap();
+    writeln!(out, "        }}").unwrap();
+    writeln!(out, "    }}").unwrap();
+}
+
+fn generate_storage(module: &Module, out: &mut String) {
+    if module.storage.is_empty() {
+        return;
+    }
+    writeln!(out, "    pub mod storage {{").unwrap();
+    imports(out);
+    writeln!(out, "        use {}::{{Api, WsResult}};", CRATE).unwrap();
+    writeln!(out, "        use {}::extrinsic::node_primitives::Signature;", CRATE).unwrap();
+    writeln!(out, "        use {}::extrinsic::primitives::crypto::Pair;", CRATE).unwrap();
+
+    for storage in &module.storage {
+        let (keys, value) = match &storage.ty {
+            StorageType::Plain(value) => (vec![], value),
+            StorageType::Map { key, value, .. } => (vec![key], value),
+            StorageType::DoubleMap { key1, key2, value, .. } => (vec![key1, key2], value),
+        };
+        let (keys, value) = match (keys.iter().map(|k| rust_type(k)).collect::<Option<Vec<_>>>(), rust_type(value)) {
+            (Some(keys), Some(value)) => (keys, value),
+            _ => {
+                writeln!(out).unwrap();
+                writeln!(out, "        // {}: nested calls have no fixed type, skipped", storage.name).unwrap();
+                continue;
+            }
+        };
+
+        let mut params = vec!["api: &Api<P>".to_string()];
+        params.extend(keys.iter().enumerate().map(|(i, k)| format!("key{}: &{}", i + 1, k)));
+        params.push("at: Option<Hash>".to_string());
+        let request = match keys.len() {
+            0 => format!("api.get_storage_value(\"{}\", \"{}\", at)", module.name, storage.name),
+            1 => format!("api.get_storage_map(\"{}\", \"{}\", key1, at)", module.name, storage.name),
+            _ => format!(
+                "api.get_storage_double_map(\"{}\", \"{}\", key1, key2, at)",
+                module.name, storage.name
+            ),
+        };
+
+        writeln!(out).unwrap();
+        writeln!(out, "        pub fn {}<P>({}) -> WsResult<Option<{}>>", ident(&snake_case(&storage.name)), params.join(", "), value).unwrap();
+        writeln!(out, "        where").unwrap();
+        writeln!(out, "            P: Pair,").unwrap();
+        writeln!(out, "            Signature: From<P::Signature>,").unwrap();
+        writeln!(out, "        {{").unwrap();
+        writeln!(out, "            {}", request).unwrap();
+        writeln!(out, "        }}").unwrap();
+    }
+    writeln!(out, "    }}").unwrap();
+}
+
+/// Maps a metadata type string to the Rust type used in the generated code.
+/// Returns `None` for types containing nested runtime calls, which have no fixed type.
+fn rust_type(ty: &str) -> Option<String> {
+    let ty = normalize_type(ty);
+    let mut out = String::new();
+    let mut token = String::new();
+    for c in ty.chars().chain(std::iter::once(' ')) {
+        if c.is_alphanumeric() || c == '_' {
+            token.push(c);
+            continue;
+        }
+        match token.as_str() {
+            "Call" | "Proposal" => return None,
+            "Address" => out.push_str("GenericAddress"),
+            t => out.push_str(t),
+        }
+        token.clear();
+        out.push(c);
+    }
+    Some(out.trim_end().to_string())
+}
+
+/// Turns a module or storage name like `BridgeEOS` or `FreeBalance` into `bridge_eos`
+/// respectively `free_balance`.
+pub fn snake_case(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut out = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let prev_lower = chars[i - 1].is_lowercase() || chars[i - 1].is_numeric();
+            let next_lower = chars.get(i + 1).map(|n| n.is_lowercase()).unwrap_or(false);
+            if prev_lower || (chars[i - 1].is_uppercase() && next_lower) {
+                out.push('_');
+            }
+        }
+        out.extend(c.to_lowercase());
+    }
+    out
+}
+
+/// Escapes names that are Rust keywords.
+fn ident(name: &str) -> String {
+    match name {
+        "as" | "break" | "const" | "continue" | "crate" | "else" | "enum" | "extern" | "false"
+        | "fn" | "for" | "if" | "impl" | "in" | "let" | "loop" | "match" | "mod" | "move"
+        | "mut" | "pub" | "ref" | "return" | "static" | "struct" | "trait" | "true" | "type"
+        | "unsafe" | "use" | "where" | "while" | "dyn" | "abstract" | "become" | "box" | "do"
+        | "final" | "macro" | "override" | "priv" | "typeof" | "unsized" | "virtual"
+        | "yield" | "async" | "await" | "try" => format!("r#{}", name),
+        _ => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node_metadata::{Arg, Call, Event, Storage};
+    use crate::utils::StorageHasher;
+
+    fn metadata() -> NodeMetadata {
+        vec![
+            Module {
+                name: "Sudo".to_string(),
+                calls: vec![Call {
+                    name: "sudo".to_string(),
+                    args: vec![Arg { name: "proposal".to_string(), ty: "Box<T::Proposal>".to_string() }],
+                }],
+                events: vec![],
+                storage_prefix: "Sudo".to_string(),
+                storage: vec![Storage {
+                    name: "Key".to_string(),
+                    ty: StorageType::Plain("T::AccountId".to_string()),
+                    default: None,
+                }],
+                errors: vec![],
+            },
+            Module {
+                name: "Balances".to_string(),
+                calls: vec![Call {
+                    name: "transfer".to_string(),
+                    args: vec![
+                        Arg { name: "dest".to_string(), ty: "<T::Lookup as StaticLookup>::Source".to_string() },
+                        Arg { name: "value".to_string(), ty: "Compact<T::Balance>".to_string() },
+                    ],
+                }],
+                events: vec![
+                    Event { name: "NewAccount".to_string(), args: vec!["AccountId".to_string(), "Balance".to_string()] },
+                    Event { name: "Dummy".to_string(), args: vec![] },
+                ],
+                storage_prefix: "Balances".to_string(),
+                storage: vec![Storage {
+                    name: "FreeBalance".to_string(),
+                    ty: StorageType::Map {
+                        hasher: StorageHasher::Blake2_256,
+                        key: "T::AccountId".to_string(),
+                        value: "T::Balance".to_string(),
+                    },
+                    default: Some(vec![0; 16]),
+                }, Storage {
+                    name: "Reserved".to_string(),
+                    ty: StorageType::DoubleMap {
+                        hasher: StorageHasher::Blake2_256,
+                        key1: "T::AccountId".to_string(),
+                        key2: "T::BlockNumber".to_string(),
+                        value: "T::Balance".to_string(),
+                        key2_hasher: StorageHasher::Twox64Concat,
+                    },
+                    default: None,
+                }],
+                errors: vec!["InsufficientBalance".to_string()],
+            },
+        ]
+    }
+
+    #[test]
+    fn test_snake_case() {
+        assert_eq!(snake_case("BridgeEOS"), "bridge_eos");
+        assert_eq!(snake_case("FreeBalance"), "free_balance");
+        assert_eq!(snake_case("ImOnline"), "im_online");
+        assert_eq!(snake_case("EOSBridge"), "eos_bridge");
+    }
+
+    #[test]
+    fn test_rust_type() {
+        assert_eq!(rust_type("<T::Lookup as StaticLookup>::Source"), Some("GenericAddress".to_string()));
+        assert_eq!(rust_type("Vec<(T::AccountId, T::Balance)>"), Some("Vec<(AccountId, Balance)>".to_string()));
+        assert_eq!(rust_type("[u8; 32]"), Some("[u8; 32]".to_string()));
+        assert_eq!(rust_type("Box<<T as Trait>::Proposal>"), None);
+        assert_eq!(rust_type("Vec<<T as Trait>::Call>"), None);
+    }
+
+    #[test]
+    fn test_generate() {
+        let code = generate(&metadata());
+        assert!(code.contains("pub mod sudo {"));
+        assert!(code.contains("pub fn sudo<Call0: Encode>(proposal: Call0) -> ([u8; 2], Call0) {"));
+        assert!(code.contains(
+            "pub fn transfer(dest: GenericAddress, value: Compact<Balance>) -> ([u8; 2], GenericAddress, Compact<Balance>) {"
+        ));
+        assert!(code.contains("([MODULE_INDEX, 0], dest, value)"));
+        assert!(code.contains("NewAccount(AccountId, Balance),"));
+        assert!(code.contains("1 => Ok(Event::Dummy),"));
+        assert!(code.contains("pub fn free_balance<P>(api: &Api<P>, key1: &AccountId, at: Option<Hash>) -> WsResult<Option<Balance>>"));
+        assert!(code.contains("api.get_storage_map(\"Balances\", \"FreeBalance\", key1, at)"));
+        assert!(code.contains("pub fn key<P>(api: &Api<P>, at: Option<Hash>) -> WsResult<Option<AccountId>>"));
+    }
+
+    #[test]
+    fn test_generate_skips_nested_calls() {
+        let mut metadata = metadata();
+        metadata[0].events = vec![Event { name: "Sudid".to_string(), args: vec!["Box<T::Proposal>".to_string()] }];
+        metadata[0].storage.push(Storage {
+            name: "Scheduled".to_string(),
+            ty: StorageType::Plain("Vec<T::Proposal>".to_string()),
+            default: None,
+        });
+
+        let code = generate(&metadata);
+        assert!(!code.contains("Vec<u8>"));
+        assert!(code.contains("// Sudid: has a nested call argument, not decodable"));
+        assert!(code.contains("0 => Err(\"Sudo::Sudid has a nested call argument\".into()),"));
+        assert!(code.contains("// Scheduled: nested calls have no fixed type, skipped"));
+        assert!(!code.contains("pub fn scheduled"));
+    }
+
+    /// `tests/codegen.rs` compiles the fixture against the current `Api`, so regenerate it
+    /// whenever the generated code changes.
+    #[test]
+    fn test_generate_matches_fixture() {
+        assert_eq!(generate(&metadata()), include_str!("test_data/codegen_runtime.rs"));
+    }
+}
@@ -0,0 +1,277 @@
+// Copyright 2019 Liebi Technologies.
+// This file is part of Bifrost.
+
+// Bifrost is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Bifrost is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Bifrost.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Decodes SCALE bytes into a `Value` tree according to metadata type strings.
+
+use super::{CustomType, Error, TypeDef, TypeRegistry};
+use crate::extrinsic::xt_primitives::{read_bytes, GenericAddress};
+use crate::utils::hexstr_to_opt_vec;
+use codec::{Compact, Decode, Input};
+use indices::address::Address;
+use serde_json::json;
+
+/// A decoded value. Byte vectors and arrays are kept as `Bytes` instead of a sequence of numbers.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Uint(u128),
+    Int(i128),
+    Str(String),
+    Bytes(Vec<u8>),
+    Seq(Vec<Value>),
+    Tuple(Vec<Value>),
+    Option(Option<Box<Value>>),
+    Struct(Vec<(String, Value)>),
+    Enum { name: String, fields: Vec<Value> },
+}
+
+impl Value {
+    /// Converts to json. Numbers that don't fit a json number and bytes become strings.
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::Bool(b) => json!(b),
+            Value::Uint(v) if *v <= u64::max_value() as u128 => json!(*v as u64),
+            Value::Uint(v) => json!(v.to_string()),
+            Value::Int(v) if *v >= i64::min_value() as i128 && *v <= i64::max_value() as i128 => {
+                json!(*v as i64)
+            }
+            Value::Int(v) => json!(v.to_string()),
+            Value::Str(s) => json!(s),
+            Value::Bytes(b) => json!(format!("0x{}", hex::encode(b))),
+            Value::Seq(values) | Value::Tuple(values) => {
+                serde_json::Value::Array(values.iter().map(Value::to_json).collect())
+            }
+            Value::Option(None) => serde_json::Value::Null,
+            Value::Option(Some(v)) => v.to_json(),
+            Value::Struct(fields) => serde_json::Value::Object(
+                fields.iter().map(|(name, v)| (name.clone(), v.to_json())).collect(),
+            ),
+            Value::Enum { name, fields } => match fields.len() {
+                0 => json!(name),
+                1 => json!({ name.as_str(): fields[0].to_json() }),
+                _ => json!({ name.as_str(): fields.iter().map(Value::to_json).collect::<Vec<_>>() }),
+            },
+        }
+    }
+}
+
+/// Decodes the hex string returned by the node, e.g. a `get_storage` result. Returns `None`
+/// for `null`, i.e. an absent key.
+pub fn decode_hexstr(ty: &str, registry: &TypeRegistry, hexstr: String) -> Result<Option<Value>, Error> {
+    let bytes = hexstr_to_opt_vec(hexstr).map_err(|e| Error::InvalidValue {
+        ty: ty.to_string(),
+        value: format!("{:?}", e),
+    })?;
+    bytes.map(|bytes| decode_bytes(ty, registry, &mut bytes.as_slice())).transpose()
+}
+
+/// Decodes a value of the type given by the metadata type string `ty` from `input`.
+/// Custom types are looked up in `registry`.
+pub fn decode_bytes<I: Input>(ty: &str, registry: &TypeRegistry, input: &mut I) -> Result<Value, Error> {
+    decode_value(&TypeDef::parse(ty), registry, input)
+}
+
+pub fn decode_value<I: Input>(ty: &TypeDef, registry: &TypeRegistry, input: &mut I) -> Result<Value, Error> {
+    let invalid = |e: codec::Error| Error::InvalidValue { ty: format!("{:?}", ty), value: format!("{:?}", e) };
+
+    let value = match ty {
+        TypeDef::Bool => Value::Bool(bool::decode(input).map_err(invalid)?),
+        TypeDef::Uint(bits) => {
+            let mut buf = [0u8; 16];
+            input.read(&mut buf[..bits / 8]).map_err(invalid)?;
+            Value::Uint(u128::from_le_bytes(buf))
+        }
+        TypeDef::Int(bits) => {
+            let mut buf = [0u8; 16];
+            input.read(&mut buf[..bits / 8]).map_err(invalid)?;
+            // sign extend
+            if buf[bits / 8 - 1] & 0x80 != 0 {
+                for b in buf[bits / 8..].iter_mut() {
+                    *b = 0xff;
+                }
+            }
+            Value::Int(i128::from_le_bytes(buf))
+        }
+        TypeDef::Str => Value::Str(String::decode(input).map_err(invalid)?),
+        TypeDef::Compact(_) => Value::Uint(Compact::<u128>::decode(input).map_err(invalid)?.0),
+        TypeDef::Vec(inner) => {
+            let len = Compact::<u32>::decode(input).map_err(invalid)?.0 as usize;
+            if **inner == TypeDef::Uint(8) {
+                Value::Bytes(read_bytes(input, len).map_err(invalid)?)
+            } else {
+                let mut values = Vec::new();
+                for _ in 0..len {
+                    values.push(decode_value(inner, registry, input)?);
+                }
+                Value::Seq(values)
+            }
+        }
+        TypeDef::Option(inner) => match (input.read_byte().map_err(invalid)?, &**inner) {
+            (0, _) => Value::Option(None),
+            (1, inner) => Value::Option(Some(Box::new(decode_value(inner, registry, input)?))),
+            _ => return Err(invalid("invalid Option tag".into())),
+        },
+        TypeDef::Tuple(types) => {
+            let mut values = Vec::new();
+            for t in types {
+                values.push(decode_value(t, registry, input)?);
+            }
+            Value::Tuple(values)
+        }
+        TypeDef::Array(inner, len) => {
+            if **inner == TypeDef::Uint(8) {
+                Value::Bytes(read_bytes(input, *len).map_err(invalid)?)
+            } else {
+                let mut values = Vec::new();
+                for _ in 0..*len {
+                    values.push(decode_value(inner, registry, input)?);
+                }
+                Value::Seq(values)
+            }
+        }
+        TypeDef::Named(name) if registry.get(name).is_some() => {
+            decode_custom(registry.get(name).unwrap(), registry, input)?
+        }
+        TypeDef::Named(name) => match name.as_str() {
+            "Address" | "LookupSource" => match GenericAddress::decode(input).map_err(invalid)? {
+                Address::Id(account) => Value::Enum {
+                    name: "Id".to_string(),
+                    fields: vec![Value::Bytes(AsRef::<[u8]>::as_ref(&account).to_vec())],
+                },
+                Address::Index(index) => Value::Enum {
+                    name: "Index".to_string(),
+                    fields: vec![Value::Uint(index.into())],
+                },
+            },
+            _ => match TypeDef::resolve_alias(name) {
+                Some(def) => decode_value(&def, registry, input)?,
+                None => return Err(Error::UnknownType(name.clone())),
+            },
+        },
+    };
+    Ok(value)
+}
+
+fn decode_custom<I: Input>(ty: &CustomType, registry: &TypeRegistry, input: &mut I) -> Result<Value, Error> {
+    let value = match ty {
+        CustomType::Alias(def) => decode_value(def, registry, input)?,
+        CustomType::Struct(fields) => {
+            let mut values = Vec::new();
+            for (name, def) in fields {
+                values.push((name.clone(), decode_value(def, registry, input)?));
+            }
+            Value::Struct(values)
+        }
+        CustomType::Enum(variants) => {
+            let index = input.read_byte().map_err(|e| Error::InvalidValue {
+                ty: format!("{:?}", ty),
+                value: format!("{:?}", e),
+            })?;
+            let (name, def) = variants.get(index as usize).ok_or_else(|| Error::InvalidValue {
+                ty: format!("{:?}", ty),
+                value: format!("variant index {}", index),
+            })?;
+            let fields = match def {
+                Some(def) => vec![decode_value(def, registry, input)?],
+                None => vec![],
+            };
+            Value::Enum { name: name.clone(), fields }
+        }
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::Encode;
+
+    fn decode(ty: &str, bytes: Vec<u8>) -> Result<Value, Error> {
+        decode_bytes(ty, &TypeRegistry::new(), &mut bytes.as_slice())
+    }
+
+    #[test]
+    fn test_decode_primitives() {
+        assert_eq!(decode("bool", vec![1]), Ok(Value::Bool(true)));
+        assert_eq!(decode("T::Balance", 1230u128.encode()), Ok(Value::Uint(1230)));
+        assert_eq!(decode("u16", 65535u16.encode()), Ok(Value::Uint(65535)));
+        assert_eq!(decode("i32", (-5i32).encode()), Ok(Value::Int(-5)));
+        assert_eq!(decode("Compact<Balance>", vec![0x39, 0x13]), Ok(Value::Uint(1230)));
+        assert!(decode("u64", vec![1, 2]).is_err());
+        assert_eq!(decode("Foo", vec![]), Err(Error::UnknownType("Foo".to_string())));
+    }
+
+    #[test]
+    fn test_decode_composites() {
+        let bytes = vec![([1u8; 32], 5u128), ([2u8; 32], 6u128)].encode();
+        assert_eq!(
+            decode("Vec<(AccountId, Balance)>", bytes),
+            Ok(Value::Seq(vec![
+                Value::Tuple(vec![Value::Bytes(vec![1u8; 32]), Value::Uint(5)]),
+                Value::Tuple(vec![Value::Bytes(vec![2u8; 32]), Value::Uint(6)]),
+            ]))
+        );
+        assert_eq!(decode("Vec<u8>", vec![8, 1, 2]), Ok(Value::Bytes(vec![1, 2])));
+        // a length prefix beyond the input fails instead of allocating it
+        let mut huge = Compact(u32::max_value()).encode();
+        huge.extend_from_slice(&[1, 2]);
+        assert!(decode("Vec<u8>", huge).is_err());
+        assert!(decode("[u8; 4]", vec![1, 2]).is_err());
+        assert_eq!(decode("Option<u32>", vec![0]), Ok(Value::Option(None)));
+        assert_eq!(decode("Option<u32>", Some(3u32).encode()), Ok(Value::Option(Some(Box::new(Value::Uint(3))))));
+        assert_eq!(decode("Option<bool>", Some(false).encode()), Ok(Value::Option(Some(Box::new(Value::Bool(false))))));
+        assert_eq!(
+            decode("(Option<bool>, u8)", (Some(true), 7u8).encode()),
+            Ok(Value::Tuple(vec![Value::Option(Some(Box::new(Value::Bool(true)))), Value::Uint(7)]))
+        );
+        assert_eq!(decode("Text", "ab".encode()), Ok(Value::Str("ab".to_string())));
+    }
+
+    #[test]
+    fn test_decode_hexstr_to_json() {
+        let value = decode_hexstr("Vec<(u32, u128)>", &TypeRegistry::new(), "\"0x040100000002000000000000000000000000000000\"".to_string()).unwrap();
+        assert_eq!(value.map(|v| v.to_json()), Some(json!([[1, 2]])));
+        assert_eq!(decode_hexstr("u32", &TypeRegistry::new(), "null".to_string()), Ok(None));
+        assert_eq!(Value::Uint(u128::max_value()).to_json(), json!("340282366920938463463374607431768211455"));
+        assert_eq!(Value::Enum { name: "Index".to_string(), fields: vec![Value::Uint(1)] }.to_json(), json!({"Index": 1}));
+    }
+
+    #[test]
+    fn test_decode_custom_types() {
+        let registry = TypeRegistry::from_json(
+            r#"{
+                "ProducerKey": { "producer_name": "u64", "block_signing_key": "Vec<u8>" },
+                "Message": { "_enum": { "Ping": "Null", "Text": "Vec<u8>" } }
+            }"#,
+        )
+        .unwrap();
+        let decode = |ty: &str, bytes: Vec<u8>| decode_bytes(ty, &registry, &mut bytes.as_slice());
+
+        assert_eq!(
+            decode("ProducerKey", (2u64, vec![1u8]).encode()),
+            Ok(Value::Struct(vec![
+                ("producer_name".to_string(), Value::Uint(2)),
+                ("block_signing_key".to_string(), Value::Bytes(vec![1])),
+            ]))
+        );
+        assert_eq!(decode("Message", vec![0]), Ok(Value::Enum { name: "Ping".to_string(), fields: vec![] }));
+        assert_eq!(
+            decode("Message", vec![1, 4, 7]).map(|v| v.to_json()),
+            Ok(json!({ "Text": "0x07" }))
+        );
+        assert!(decode("Message", vec![2]).is_err());
+    }
+}
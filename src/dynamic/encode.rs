@@ -0,0 +1,388 @@
+// Copyright 2019 Liebi Technologies.
+// This file is part of Bifrost.
+
+// Bifrost is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Bifrost is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Bifrost.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Encodes json values according to metadata type strings.
+//!
+//! Numbers may be given as json numbers or as decimal or `0x` prefixed hex strings, so that
+//! `u128` balances don't lose precision. Byte vectors and arrays take `0x` prefixed hex strings,
+//! plain strings (utf8 bytes) or arrays of numbers. Accounts take hex or SS58 strings.
+
+use super::{CustomType, Error, RawCall, TypeDef, TypeRegistry};
+use crate::extrinsic::xt_primitives::GenericAddress;
+use crate::node_metadata::{call_module_index, NodeMetadata};
+use codec::{Compact, Encode};
+use node_primitives::AccountId;
+use primitives::crypto::Ss58Codec;
+use primitives::sr25519;
+use serde_json::Value;
+
+/// Composes a call from json arguments. `args` is either an array with one value per call
+/// argument, or an object with the argument names as keys. Custom types are looked up in `registry`.
+pub fn compose_call_json(
+    metadata: &NodeMetadata,
+    registry: &TypeRegistry,
+    module: &str,
+    call: &str,
+    args: &Value,
+) -> Result<RawCall, Error> {
+    let module_index = call_module_index(metadata, module)
+        .ok_or_else(|| Error::ModuleNotFound(module.to_string()))?;
+    let calls = &metadata.iter().find(|m| m.name == module).unwrap().calls;
+    let call_index = calls
+        .iter()
+        .position(|c| c.name == call)
+        .ok_or_else(|| Error::CallNotFound(call.to_string()))?;
+    let call_args = &calls[call_index].args;
+
+    let values: Vec<&Value> = match args {
+        Value::Array(values) => values.iter().collect(),
+        Value::Object(map) => call_args
+            .iter()
+            .map(|a| map.get(&a.name).unwrap_or(&Value::Null))
+            .collect(),
+        Value::Null => vec![],
+        value => vec![value],
+    };
+    if values.len() != call_args.len() {
+        return Err(Error::ArgumentCount(call_args.len(), values.len()));
+    }
+
+    let mut out = vec![module_index as u8, call_index as u8];
+    for (arg, value) in call_args.iter().zip(values) {
+        encode_json(&arg.ty, value, registry, &mut out)?;
+    }
+    Ok(RawCall(out))
+}
+
+/// Encodes `value` as the type given by the metadata type string `ty` and appends it to `out`.
+pub fn encode_json(ty: &str, value: &Value, registry: &TypeRegistry, out: &mut Vec<u8>) -> Result<(), Error> {
+    encode_value(&TypeDef::parse(ty), value, registry, out)
+}
+
+pub fn encode_value(
+    ty: &TypeDef,
+    value: &Value,
+    registry: &TypeRegistry,
+    out: &mut Vec<u8>,
+) -> Result<(), Error> {
+    let invalid = || Error::InvalidValue { ty: format!("{:?}", ty), value: value.to_string() };
+
+    match ty {
+        TypeDef::Bool => value.as_bool().ok_or_else(invalid)?.encode_to(out),
+        TypeDef::Uint(bits) => {
+            let v = to_u128(value).ok_or_else(invalid)?;
+            if *bits < 128 && v >> *bits != 0 {
+                return Err(invalid());
+            }
+            out.extend_from_slice(&v.to_le_bytes()[..bits / 8]);
+        }
+        TypeDef::Int(bits) => {
+            let v = to_i128(value).ok_or_else(invalid)?;
+            if *bits < 128 && (v < -(1i128 << (bits - 1)) || v >= 1i128 << (bits - 1)) {
+                return Err(invalid());
+            }
+            out.extend_from_slice(&v.to_le_bytes()[..bits / 8]);
+        }
+        TypeDef::Str => value.as_str().ok_or_else(invalid)?.encode_to(out),
+        TypeDef::Compact(inner) => {
+            let v = to_u128(value).ok_or_else(invalid)?;
+            match uint_bits(inner, registry) {
+                Some(bits) if bits < 128 && v >> bits != 0 => return Err(invalid()),
+                _ => Compact(v).encode_to(out),
+            }
+        }
+        TypeDef::Vec(inner) => {
+            if **inner == TypeDef::Uint(8) {
+                to_bytes(value).ok_or_else(invalid)?.encode_to(out);
+            } else {
+                let values = value.as_array().ok_or_else(invalid)?;
+                Compact(values.len() as u32).encode_to(out);
+                for v in values {
+                    encode_value(inner, v, registry, out)?;
+                }
+            }
+        }
+        TypeDef::Option(inner) => match (value, &**inner) {
+            (Value::Null, _) => out.push(0),
+            (v, inner) => {
+                out.push(1);
+                encode_value(inner, v, registry, out)?;
+            }
+        },
+        TypeDef::Tuple(types) => {
+            let values = match value {
+                Value::Array(values) => values.iter().collect::<Vec<_>>(),
+                Value::Null => vec![],
+                v => vec![v],
+            };
+            if values.len() != types.len() {
+                return Err(invalid());
+            }
+            for (t, v) in types.iter().zip(values) {
+                encode_value(t, v, registry, out)?;
+            }
+        }
+        TypeDef::Array(inner, len) => {
+            if **inner == TypeDef::Uint(8) {
+                let bytes = to_bytes(value).ok_or_else(invalid)?;
+                if bytes.len() != *len {
+                    return Err(invalid());
+                }
+                out.extend_from_slice(&bytes);
+            } else {
+                let values = value.as_array().filter(|v| v.len() == *len).ok_or_else(invalid)?;
+                for v in values {
+                    encode_value(inner, v, registry, out)?;
+                }
+            }
+        }
+        TypeDef::Named(name) if registry.get(name).is_some() => {
+            encode_custom(registry.get(name).unwrap(), value, registry, out)?
+        }
+        TypeDef::Named(name) => match name.as_str() {
+            "AccountId" => out.extend_from_slice(&to_account(value).ok_or_else(invalid)?),
+            "Address" | "LookupSource" => match value {
+                Value::Number(n) => {
+                    let index = n.as_u64().filter(|i| *i <= u32::max_value() as u64).ok_or_else(invalid)?;
+                    GenericAddress::Index(index as u32).encode_to(out)
+                }
+                v => GenericAddress::from(AccountId::from(to_account(v).ok_or_else(invalid)?)).encode_to(out),
+            },
+            // nested calls must be given already encoded, e.g. a `RawCall` as hex
+            "Call" | "Proposal" => out.extend_from_slice(&to_bytes(value).ok_or_else(invalid)?),
+            _ => match TypeDef::resolve_alias(name) {
+                Some(def) => encode_value(&def, value, registry, out)?,
+                None => return Err(Error::UnknownType(name.clone())),
+            },
+        },
+    }
+    Ok(())
+}
+
+fn encode_custom(
+    ty: &CustomType,
+    value: &Value,
+    registry: &TypeRegistry,
+    out: &mut Vec<u8>,
+) -> Result<(), Error> {
+    let invalid = || Error::InvalidValue { ty: format!("{:?}", ty), value: value.to_string() };
+
+    match ty {
+        CustomType::Alias(def) => encode_value(def, value, registry, out)?,
+        CustomType::Struct(fields) => match value {
+            Value::Object(map) => {
+                for (name, def) in fields {
+                    encode_value(def, map.get(name).unwrap_or(&Value::Null), registry, out)?;
+                }
+            }
+            Value::Array(values) if values.len() == fields.len() => {
+                for ((_, def), v) in fields.iter().zip(values) {
+                    encode_value(def, v, registry, out)?;
+                }
+            }
+            _ => return Err(invalid()),
+        },
+        CustomType::Enum(variants) => {
+            // either the variant name, or an object with the variant name as only key
+            let (name, content) = match value {
+                Value::String(name) => (name, &Value::Null),
+                Value::Object(map) if map.len() == 1 => map.iter().next().unwrap(),
+                _ => return Err(invalid()),
+            };
+            let index = variants.iter().position(|(v, _)| v == name).ok_or_else(invalid)?;
+            out.push(index as u8);
+            if let Some(def) = &variants[index].1 {
+                encode_value(def, content, registry, out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The width of the unsigned integer `ty` stands for, following aliases.
+fn uint_bits(ty: &TypeDef, registry: &TypeRegistry) -> Option<usize> {
+    match ty {
+        TypeDef::Uint(bits) => Some(*bits),
+        TypeDef::Named(name) => match registry.get(name) {
+            Some(CustomType::Alias(def)) => uint_bits(def, registry),
+            Some(_) => None,
+            None => TypeDef::resolve_alias(name).and_then(|def| uint_bits(&def, registry)),
+        },
+        _ => None,
+    }
+}
+
+fn to_u128(value: &Value) -> Option<u128> {
+    match value {
+        Value::Number(n) => n.as_u64().map(u128::from),
+        Value::String(s) if s.starts_with("0x") => u128::from_str_radix(&s[2..], 16).ok(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn to_i128(value: &Value) -> Option<i128> {
+    match value {
+        Value::Number(n) => n.as_i64().map(i128::from),
+        Value::String(s) if s.starts_with("0x") => i128::from_str_radix(&s[2..], 16).ok(),
+        Value::String(s) if s.starts_with("-0x") => i128::from_str_radix(&s[3..], 16).ok().map(|v| -v),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn to_bytes(value: &Value) -> Option<Vec<u8>> {
+    match value {
+        Value::String(s) if s.starts_with("0x") => hex::decode(&s[2..]).ok(),
+        Value::String(s) => Some(s.as_bytes().to_vec()),
+        Value::Array(values) => values
+            .iter()
+            .map(|v| v.as_u64().filter(|b| *b <= 0xff).map(|b| b as u8))
+            .collect(),
+        _ => None,
+    }
+}
+
+fn to_account(value: &Value) -> Option<[u8; 32]> {
+    let s = value.as_str()?;
+    if s.starts_with("0x") {
+        let bytes = hex::decode(&s[2..]).ok().filter(|b| b.len() == 32)?;
+        let mut account = [0u8; 32];
+        account.copy_from_slice(&bytes);
+        Some(account)
+    } else {
+        sr25519::Public::from_ss58check(s).ok().map(|public| public.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node_metadata::{Arg, Call, Module};
+    use keyring::AccountKeyring;
+    use serde_json::json;
+
+    fn encode(ty: &str, value: Value) -> Result<Vec<u8>, Error> {
+        let mut out = Vec::new();
+        encode_json(ty, &value, &TypeRegistry::new(), &mut out).map(|_| out)
+    }
+
+    #[test]
+    fn test_encode_primitives() {
+        assert_eq!(encode("bool", json!(true)), Ok(vec![1]));
+        assert_eq!(encode("u32", json!(1)), Ok(1u32.encode()));
+        assert_eq!(encode("T::Balance", json!("340282366920938463463374607431768211455")), Ok(u128::max_value().encode()));
+        assert_eq!(encode("u64", json!("0x0100")), Ok(256u64.encode()));
+        assert_eq!(encode("i16", json!(-2)), Ok((-2i16).encode()));
+        assert_eq!(encode("i16", json!("0x10")), Ok(16i16.encode()));
+        assert_eq!(encode("i16", json!("-0x10")), Ok((-16i16).encode()));
+        assert!(encode("u8", json!(256)).is_err());
+        assert!(encode("Foo", json!(1)) == Err(Error::UnknownType("Foo".to_string())));
+    }
+
+    #[test]
+    fn test_encode_composites() {
+        assert_eq!(encode("Compact<T::Balance>", json!(1230)), Ok(vec![0x39, 0x13]));
+        assert_eq!(encode("Compact<u32>", json!(4294967295u64)), Ok(Compact(u32::max_value()).encode()));
+        assert!(encode("Compact<u32>", json!(4294967296u64)).is_err());
+        assert!(encode("Compact<T::BlockNumber>", json!(4294967296u64)).is_err());
+        assert_eq!(encode("Vec<u8>", json!("0x0102")), Ok(vec![8, 1, 2]));
+        assert_eq!(encode("Vec<u8>", json!("ab")), Ok(vec![8, b'a', b'b']));
+        assert_eq!(encode("Vec<(u32, bool)>", json!([[1, true]])), Ok(vec![(1u32, true)].encode()));
+        assert_eq!(encode("Option<u32>", json!(null)), Ok(vec![0]));
+        assert_eq!(encode("Option<u32>", json!(5)), Ok(Some(5u32).encode()));
+        assert_eq!(encode("Option<bool>", json!(false)), Ok(Some(false).encode()));
+        assert_eq!(encode("Option<bool>", json!(true)), Ok(Some(true).encode()));
+        assert_eq!(encode("[u8; 2]", json!([1, 2])), Ok(vec![1, 2]));
+        assert!(encode("[u8; 2]", json!("0x010203")).is_err());
+        assert_eq!(encode("Text", json!("ab")), Ok("ab".encode()));
+    }
+
+    #[test]
+    fn test_encode_accounts() {
+        let alice = AccountId::from(AccountKeyring::Alice);
+        let alice_hex = format!("0x{}", hex::encode(AsRef::<[u8]>::as_ref(&alice)));
+        let alice_ss58 = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+
+        assert_eq!(encode("T::AccountId", json!(alice_hex)), Ok(alice.encode()));
+        assert_eq!(encode("T::AccountId", json!(alice_ss58)), Ok(alice.encode()));
+        assert_eq!(
+            encode("<T::Lookup as StaticLookup>::Source", json!(alice_ss58)),
+            Ok(GenericAddress::from(alice.clone()).encode())
+        );
+    }
+
+    #[test]
+    fn test_compose_call_json() {
+        let meta = vec![
+            Module { name: "Sudo".to_string(), calls: vec![Call { name: "sudo".to_string(), args: vec![] }], ..Default::default() },
+            Module {
+                name: "Balances".to_string(),
+                calls: vec![
+                    Call { name: "set_balance".to_string(), args: vec![] },
+                    Call {
+                        name: "transfer".to_string(),
+                        args: vec![
+                            Arg { name: "dest".to_string(), ty: "<T::Lookup as StaticLookup>::Source".to_string() },
+                            Arg { name: "value".to_string(), ty: "Compact<T::Balance>".to_string() },
+                        ],
+                    },
+                ],
+                ..Default::default()
+            },
+        ];
+        let bob = AccountId::from(AccountKeyring::Bob);
+        let bob_hex = format!("0x{}", hex::encode(AsRef::<[u8]>::as_ref(&bob)));
+        let expected = ([1u8, 1u8], GenericAddress::from(bob), Compact(1230u128)).encode();
+
+        let registry = TypeRegistry::new();
+        let positional = compose_call_json(&meta, &registry, "Balances", "transfer", &json!([bob_hex, 1230]));
+        assert_eq!(positional, Ok(RawCall(expected.clone())));
+        assert_eq!(positional.unwrap().encode(), expected);
+
+        let named = compose_call_json(&meta, &registry, "Balances", "transfer", &json!({"value": "1230", "dest": bob_hex}));
+        assert_eq!(named, Ok(RawCall(expected)));
+
+        assert_eq!(compose_call_json(&meta, &registry, "Balances", "transfer", &json!([1])), Err(Error::ArgumentCount(2, 1)));
+        assert_eq!(compose_call_json(&meta, &registry, "Staking", "bond", &json!([])), Err(Error::ModuleNotFound("Staking".to_string())));
+    }
+
+    #[test]
+    fn test_encode_custom_types() {
+        let registry = TypeRegistry::from_json(
+            r#"{
+                "Checksum256": "[u8; 32]",
+                "ProducerKey": { "producer_name": "u64", "block_signing_key": "Vec<u8>" },
+                "Message": { "_enum": { "Ping": "Null", "Text": "Vec<u8>" } }
+            }"#,
+        )
+        .unwrap();
+        let encode = |ty: &str, value: Value| {
+            let mut out = Vec::new();
+            encode_json(ty, &value, &registry, &mut out).map(|_| out)
+        };
+
+        assert_eq!(encode("Checksum256", json!(format!("0x{}", "00".repeat(32)))), Ok(vec![0u8; 32]));
+        assert_eq!(
+            encode("Vec<ProducerKey>", json!([{ "block_signing_key": "0x01", "producer_name": 2 }])),
+            Ok(vec![(2u64, vec![1u8])].encode())
+        );
+        assert_eq!(encode("ProducerKey", json!([2, "0x01"])), Ok((2u64, vec![1u8]).encode()));
+        assert_eq!(encode("Message", json!("Ping")), Ok(vec![0]));
+        assert_eq!(encode("Message", json!({ "Text": "0x0102" })), Ok(vec![1, 8, 1, 2]));
+        assert!(encode("Message", json!("Pong")).is_err());
+    }
+}
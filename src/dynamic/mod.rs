@@ -0,0 +1,78 @@
+// Copyright 2019 Liebi Technologies.
+// This file is part of Bifrost.
+
+// Bifrost is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Bifrost is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Bifrost.  If not, see <http://www.gnu.org/licenses/>.
+
+//! SCALE encoding and decoding driven by the type strings found in the metadata instead of Rust types.
+
+use codec::{Encode, Output};
+use std::fmt;
+
+pub use decode::{decode_bytes, decode_hexstr, Value};
+pub use encode::{compose_call_json, encode_json};
+pub use registry::{CustomType, TypeRegistry};
+pub use type_def::TypeDef;
+
+pub mod decode;
+pub mod encode;
+pub mod registry;
+pub mod type_def;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The type string could not be resolved to a known type.
+    UnknownType(String),
+    /// The value does not fit the type.
+    InvalidValue { ty: String, value: String },
+    ModuleNotFound(String),
+    CallNotFound(String),
+    /// Wrong number of call arguments (expected, got).
+    ArgumentCount(usize, usize),
+    /// No event with this (module index, event index) in the metadata.
+    EventNotFound(u8, u8),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnknownType(ty) => write!(f, "unknown type {}", ty),
+            Error::InvalidValue { ty, value } => write!(f, "invalid value {} for type {}", value, ty),
+            Error::ModuleNotFound(module) => write!(f, "module {} not found in metadata", module),
+            Error::CallNotFound(call) => write!(f, "call {} not found in module", call),
+            Error::ArgumentCount(expected, got) => {
+                write!(f, "expected {} arguments, got {}", expected, got)
+            }
+            Error::EventNotFound(module, event) => {
+                write!(f, "event {} of module {} not found in metadata", event, module)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// An already encoded call, i.e. module index, call index and arguments. Encodes to exactly
+/// these bytes, so it can be used wherever `compose_call!` output is expected.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RawCall(pub Vec<u8>);
+
+impl Encode for RawCall {
+    fn size_hint(&self) -> usize {
+        self.0.len()
+    }
+
+    fn encode_to<T: Output>(&self, dest: &mut T) {
+        dest.write(&self.0)
+    }
+}
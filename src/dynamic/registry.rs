@@ -0,0 +1,244 @@
+// Copyright 2019 Liebi Technologies.
+// This file is part of Bifrost.
+
+// Bifrost is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Bifrost is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Bifrost.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Registry of runtime specific types, loaded from a json definition file in the spirit of
+//! polkadot-js' `types.json`:
+//!
+//! ```json
+//! {
+//!     "Checksum256": "[u8; 32]",
+//!     "ProducerKey": { "producer_name": "AccountName", "block_signing_key": "PublicKey" },
+//!     "Status": { "_enum": ["Active", "Inactive"] },
+//!     "Message": { "_enum": { "Ping": "Null", "Text": "Vec<u8>" } }
+//! }
+//! ```
+//!
+//! A string defines an alias, an object a struct with its fields in order, and an object with
+//! an `_enum` key an enum, either with unit variants only or with one type per variant.
+
+use super::{Error, TypeDef};
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use std::{collections::HashMap, fmt, fs, path::Path};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CustomType {
+    Alias(TypeDef),
+    Struct(Vec<(String, TypeDef)>),
+    /// Variants with their optional content, in the order of their index.
+    Enum(Vec<(String, Option<TypeDef>)>),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TypeRegistry {
+    types: HashMap<String, CustomType>,
+}
+
+impl TypeRegistry {
+    /// A registry knowing the types of the system events every runtime emits, like
+    /// `DispatchError` and `DispatchInfo`.
+    pub fn new() -> Self {
+        let mut registry = Self::default();
+        registry.register(
+            "DispatchError",
+            CustomType::Struct(vec![
+                ("module".to_string(), TypeDef::parse("Option<u8>")),
+                ("error".to_string(), TypeDef::Uint(8)),
+            ]),
+        );
+        registry.register(
+            "DispatchClass",
+            CustomType::Enum(vec![("Normal".to_string(), None), ("Operational".to_string(), None)]),
+        );
+        registry.register(
+            "DispatchInfo",
+            CustomType::Struct(vec![
+                ("weight".to_string(), TypeDef::parse("Weight")),
+                ("class".to_string(), TypeDef::parse("DispatchClass")),
+                ("pays_fee".to_string(), TypeDef::Bool),
+            ]),
+        );
+        registry
+    }
+
+    /// Loads the type definitions from a json file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::from_json(&fs::read_to_string(path)?)?)
+    }
+
+    /// Parses type definitions as described in the module documentation.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let invalid = |name: &str| Error::InvalidValue {
+            ty: name.to_string(),
+            value: "invalid type definition".to_string(),
+        };
+        let defs = match serde_json::from_str(json) {
+            Ok(JsonDef::Map(defs)) => defs,
+            Ok(_) => return Err(invalid("type definitions")),
+            Err(e) => return Err(Error::InvalidValue { ty: "type definitions".to_string(), value: e.to_string() }),
+        };
+
+        let mut registry = Self::new();
+        for (name, def) in defs {
+            let ty = match def {
+                JsonDef::Str(alias) => CustomType::Alias(TypeDef::parse(&alias)),
+                JsonDef::Map(fields) => match fields.iter().find(|(f, _)| f == "_enum") {
+                    Some((_, JsonDef::Seq(variants))) => CustomType::Enum(
+                        variants
+                            .iter()
+                            .map(|v| v.as_str().map(|v| (v.to_string(), None)))
+                            .collect::<Option<_>>()
+                            .ok_or_else(|| invalid(&name))?,
+                    ),
+                    Some((_, JsonDef::Map(variants))) => CustomType::Enum(
+                        variants
+                            .iter()
+                            .map(|(v, ty)| ty.as_str().map(|ty| (v.clone(), variant_type(ty))))
+                            .collect::<Option<_>>()
+                            .ok_or_else(|| invalid(&name))?,
+                    ),
+                    Some(_) => return Err(invalid(&name)),
+                    None => CustomType::Struct(
+                        fields
+                            .iter()
+                            .map(|(f, ty)| ty.as_str().map(|ty| (f.clone(), TypeDef::parse(ty))))
+                            .collect::<Option<_>>()
+                            .ok_or_else(|| invalid(&name))?,
+                    ),
+                },
+                JsonDef::Seq(_) => return Err(invalid(&name)),
+            };
+            registry.register(&name, ty);
+        }
+        Ok(registry)
+    }
+
+    pub fn register(&mut self, name: &str, ty: CustomType) {
+        self.types.insert(name.to_string(), ty);
+    }
+
+    /// Adds all definitions of `other`, replacing the ones with the same name.
+    pub fn extend(&mut self, other: TypeRegistry) {
+        self.types.extend(other.types);
+    }
+
+    /// Looks up a type by its full name first, then by its name without generic parameters,
+    /// e.g. `DigestItem<Hash>` falls back to `DigestItem`.
+    pub fn get(&self, name: &str) -> Option<&CustomType> {
+        self.types.get(name).or_else(|| match name.find('<') {
+            Some(i) => self.types.get(&name[..i]),
+            None => None,
+        })
+    }
+}
+
+/// The json of a type definition. Unlike `serde_json::Value` it keeps the order of object keys,
+/// which is the order of struct fields and enum variants.
+enum JsonDef {
+    Str(String),
+    Seq(Vec<JsonDef>),
+    Map(Vec<(String, JsonDef)>),
+}
+
+impl JsonDef {
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonDef::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonDef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct JsonDefVisitor;
+
+        impl<'de> Visitor<'de> for JsonDefVisitor {
+            type Value = JsonDef;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a type name, an object or an array")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<JsonDef, E> {
+                Ok(JsonDef::Str(v.to_string()))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<JsonDef, A::Error> {
+                let mut items = Vec::new();
+                while let Some(item) = seq.next_element()? {
+                    items.push(item);
+                }
+                Ok(JsonDef::Seq(items))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<JsonDef, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(JsonDef::Map(entries))
+            }
+        }
+
+        deserializer.deserialize_any(JsonDefVisitor)
+    }
+}
+
+fn variant_type(ty: &str) -> Option<TypeDef> {
+    match ty {
+        "Null" | "()" | "" => None,
+        ty => Some(TypeDef::parse(ty)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_from_json() {
+        let registry = TypeRegistry::from_json(
+            r#"{
+                "Checksum256": "[u8; 32]",
+                "ProducerKey": { "producer_name": "u64", "block_signing_key": "Vec<u8>" },
+                "Status": { "_enum": ["Active", "Inactive"] },
+                "Message": { "_enum": { "Ping": "Null", "Text": "Vec<u8>" } }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(registry.get("Checksum256"), Some(&CustomType::Alias(TypeDef::Array(Box::new(TypeDef::Uint(8)), 32))));
+        assert_eq!(
+            registry.get("ProducerKey"),
+            Some(&CustomType::Struct(vec![
+                ("producer_name".to_string(), TypeDef::Uint(64)),
+                ("block_signing_key".to_string(), TypeDef::Vec(Box::new(TypeDef::Uint(8)))),
+            ]))
+        );
+        assert_eq!(
+            registry.get("Status"),
+            Some(&CustomType::Enum(vec![("Active".to_string(), None), ("Inactive".to_string(), None)]))
+        );
+        assert_eq!(
+            registry.get("Message<u32>"),
+            Some(&CustomType::Enum(vec![
+                ("Ping".to_string(), None),
+                ("Text".to_string(), Some(TypeDef::Vec(Box::new(TypeDef::Uint(8))))),
+            ]))
+        );
+        assert!(TypeRegistry::from_json(r#"{ "Broken": 1 }"#).is_err());
+    }
+}
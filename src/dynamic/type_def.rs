@@ -0,0 +1,158 @@
+// Copyright 2019 Liebi Technologies.
+// This file is part of Bifrost.
+
+// Bifrost is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Bifrost is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Bifrost.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::node_metadata::normalize_type;
+
+/// Structure of a type as given by a metadata type string like `Vec<(AccountId, Balance)>`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TypeDef {
+    Bool,
+    /// Unsigned integer with the given number of bits.
+    Uint(usize),
+    /// Signed integer with the given number of bits.
+    Int(usize),
+    /// Utf8 string, encoded like `Vec<u8>`.
+    Str,
+    Compact(Box<TypeDef>),
+    Vec(Box<TypeDef>),
+    Option(Box<TypeDef>),
+    Tuple(Vec<TypeDef>),
+    Array(Box<TypeDef>, usize),
+    /// Any other type, resolved by name when encoding.
+    Named(String),
+}
+
+impl TypeDef {
+    /// Parses a metadata type string. Trait paths like `T::` are stripped first.
+    pub fn parse(ty: &str) -> TypeDef {
+        parse_normalized(&normalize_type(ty))
+    }
+
+    /// Resolves the well known runtime aliases, e.g. `Balance` to `u128`.
+    /// `Address` is kept as a named type, as it has its own encoding.
+    pub fn resolve_alias(name: &str) -> Option<TypeDef> {
+        let u8_array = |len| TypeDef::Array(Box::new(TypeDef::Uint(8)), len);
+        let def = match name {
+            "AccountId" | "Hash" | "H256" | "SessionKey" | "AuthorityId" => u8_array(32),
+            "H160" => u8_array(20),
+            "H512" => u8_array(64),
+            "Balance" => TypeDef::Uint(128),
+            "Moment" => TypeDef::Uint(64),
+            "BlockNumber" | "Index" | "AccountIndex" | "Weight" | "Perbill" | "Permill" => {
+                TypeDef::Uint(32)
+            }
+            "SessionIndex" | "EraIndex" | "AuthorityIndex" | "PropIndex" | "ReferendumIndex"
+            | "ProposalIndex" | "MemberCount" => TypeDef::Uint(32),
+            "AuthorityWeight" | "SetId" => TypeDef::Uint(64),
+            // grandpa authorities with their weights
+            "AuthorityList" => TypeDef::Vec(Box::new(TypeDef::Tuple(vec![
+                TypeDef::Named("AuthorityId".to_string()),
+                TypeDef::Named("AuthorityWeight".to_string()),
+            ]))),
+            // the kind of an offence
+            "Kind" => u8_array(16),
+            // unit-like enum, encoded as its index
+            "VoteThreshold" => TypeDef::Uint(8),
+            "Percent" => TypeDef::Uint(8),
+            "Bytes" | "OpaqueTimeSlot" => TypeDef::Vec(Box::new(TypeDef::Uint(8))),
+            "Text" | "String" => TypeDef::Str,
+            _ => return None,
+        };
+        Some(def)
+    }
+}
+
+fn parse_normalized(ty: &str) -> TypeDef {
+    let ty = ty.trim();
+    match ty {
+        "bool" => return TypeDef::Bool,
+        "u8" | "u16" | "u32" | "u64" | "u128" => return TypeDef::Uint(ty[1..].parse().unwrap()),
+        "i8" | "i16" | "i32" | "i64" | "i128" => return TypeDef::Int(ty[1..].parse().unwrap()),
+        _ => {}
+    }
+
+    if ty.starts_with('(') && ty.ends_with(')') {
+        return TypeDef::Tuple(
+            split_top_level(&ty[1..ty.len() - 1], ',')
+                .iter()
+                .map(|t| parse_normalized(t))
+                .collect(),
+        );
+    }
+
+    if ty.starts_with('[') && ty.ends_with(']') {
+        let parts = split_top_level(&ty[1..ty.len() - 1], ';');
+        if parts.len() == 2 {
+            if let Ok(len) = parts[1].trim().parse() {
+                return TypeDef::Array(Box::new(parse_normalized(parts[0])), len);
+            }
+        }
+        return TypeDef::Named(ty.to_string());
+    }
+
+    if let (Some(start), true) = (ty.find('<'), ty.ends_with('>')) {
+        let inner = &ty[start + 1..ty.len() - 1];
+        match &ty[..start] {
+            "Vec" => return TypeDef::Vec(Box::new(parse_normalized(inner))),
+            "Compact" => return TypeDef::Compact(Box::new(parse_normalized(inner))),
+            "Option" => return TypeDef::Option(Box::new(parse_normalized(inner))),
+            "Box" => return parse_normalized(inner),
+            _ => {}
+        }
+    }
+
+    TypeDef::Named(ty.to_string())
+}
+
+/// Splits at `sep`, ignoring separators nested in `<>`, `()` or `[]`. Empty parts are dropped.
+fn split_top_level(s: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth -= 1,
+            c if c == sep && depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts.into_iter().map(|p| p.trim()).filter(|p| !p.is_empty()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_type_def() {
+        assert_eq!(TypeDef::parse("bool"), TypeDef::Bool);
+        assert_eq!(TypeDef::parse("Compact<T::Balance>"), TypeDef::Compact(Box::new(TypeDef::Named("Balance".to_string()))));
+        assert_eq!(
+            TypeDef::parse("Vec<(T::AccountId, u64)>"),
+            TypeDef::Vec(Box::new(TypeDef::Tuple(vec![TypeDef::Named("AccountId".to_string()), TypeDef::Uint(64)])))
+        );
+        assert_eq!(TypeDef::parse("[u8; 32]"), TypeDef::Array(Box::new(TypeDef::Uint(8)), 32));
+        assert_eq!(TypeDef::parse("Option<Vec<u8>>"), TypeDef::Option(Box::new(TypeDef::Vec(Box::new(TypeDef::Uint(8))))));
+        assert_eq!(TypeDef::parse("Box<<T as Trait>::Proposal>"), TypeDef::Named("Proposal".to_string()));
+        assert_eq!(TypeDef::parse("()"), TypeDef::Tuple(vec![]));
+        assert_eq!(TypeDef::parse("DigestItem<T::Hash>"), TypeDef::Named("DigestItem<Hash>".to_string()));
+    }
+}
@@ -0,0 +1,391 @@
+// Copyright 2019 Liebi Technologies.
+// This file is part of Bifrost.
+
+// Bifrost is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Bifrost is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Bifrost.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Decodes the `System::Events` storage value into `EventRecord`s, resolving modules, events
+//! and argument types from the metadata.
+
+use crate::dynamic::{decode_bytes, Error, TypeRegistry, Value};
+use crate::node_metadata::{Module, NodeMetadata};
+use crate::utils::hexstr_to_vec;
+use codec::{Compact, Decode, Input};
+use primitives::H256 as Hash;
+
+/// When in the block an event was emitted.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Phase {
+    /// While applying the extrinsic with this index.
+    ApplyExtrinsic(u32),
+    /// While finalizing the block.
+    Finalization,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventRecord {
+    pub phase: Phase,
+    pub module: String,
+    pub name: String,
+    pub args: Vec<Value>,
+    /// The SCALE encoded arguments, see `decode_args`.
+    pub raw_args: Vec<u8>,
+    pub topics: Vec<Hash>,
+}
+
+impl EventRecord {
+    /// Decodes the arguments into a Rust type, e.g. a tuple of the argument types or a struct
+    /// with the arguments as fields in order.
+    pub fn decode_args<T: Decode>(&self) -> Result<T, codec::Error> {
+        T::decode(&mut self.raw_args.as_slice())
+    }
+}
+
+/// Selects events by module and name. An empty filter matches every event.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EventFilter {
+    /// (module, event) pairs, where no event name matches every event of the module.
+    entries: Vec<(String, Option<String>)>,
+}
+
+impl EventFilter {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Matches every event of `module`.
+    pub fn module(mut self, module: &str) -> Self {
+        self.entries.push((module.to_string(), None));
+        self
+    }
+
+    /// Matches the event `name` of `module`.
+    pub fn event(mut self, module: &str, name: &str) -> Self {
+        self.entries.push((module.to_string(), Some(name.to_string())));
+        self
+    }
+
+    pub fn matches(&self, record: &EventRecord) -> bool {
+        self.entries.is_empty()
+            || self.entries.iter().any(|(module, name)| {
+                *module == record.module && name.as_ref().map_or(true, |n| *n == record.name)
+            })
+    }
+}
+
+/// Why an extrinsic failed, as carried by `System::ExtrinsicFailed`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DispatchError {
+    /// `module` is the index of the module in the metadata for module errors, `error` the
+    /// index of the error in the module.
+    Error { module: Option<u8>, error: u8 },
+    /// The error could not be decoded, e.g. because the runtime uses a different layout.
+    /// Holds the encoded arguments of the event.
+    Undecodable(Vec<u8>),
+}
+
+impl DispatchError {
+    fn from_value(value: &Value) -> Option<Self> {
+        let fields = match value {
+            Value::Struct(fields) => fields,
+            _ => return None,
+        };
+        let field = |name: &str| fields.iter().find(|(n, _)| n == name).map(|(_, v)| v);
+        let module = match field("module")? {
+            Value::Option(None) => None,
+            Value::Option(Some(v)) => match **v {
+                Value::Uint(m) => Some(m as u8),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        match field("error")? {
+            Value::Uint(error) => Some(DispatchError::Error { module, error: *error as u8 }),
+            _ => None,
+        }
+    }
+
+    /// The module and error name of a module error.
+    pub fn describe(&self, metadata: &NodeMetadata) -> Option<(String, String)> {
+        match self {
+            DispatchError::Error { module: Some(module), error } => {
+                let module = metadata.get(*module as usize)?;
+                let error = module.errors.get(*error as usize)?;
+                Some((module.name.clone(), error.clone()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The outcome of an extrinsic according to the `System::ExtrinsicSuccess` or
+/// `System::ExtrinsicFailed` event among its events. `None` if there is neither.
+pub fn dispatch_result(events: &[EventRecord]) -> Option<Result<(), DispatchError>> {
+    events.iter().filter(|e| e.module == "System").find_map(|e| match e.name.as_str() {
+        "ExtrinsicSuccess" => Some(Ok(())),
+        "ExtrinsicFailed" => {
+            let error = e.args.get(0).and_then(DispatchError::from_value);
+            // an undecodable error still means the extrinsic failed
+            Some(Err(error.unwrap_or_else(|| DispatchError::Undecodable(e.raw_args.clone()))))
+        }
+        _ => None,
+    })
+}
+
+/// Decodes the hex encoded `System::Events` value, as returned by `get_storage` or delivered
+/// by `subscribe_events`.
+pub fn decode_events_hexstr(
+    metadata: &NodeMetadata,
+    registry: &TypeRegistry,
+    hexstr: String,
+) -> Result<Vec<EventRecord>, Error> {
+    let bytes = hexstr_to_vec(hexstr).map_err(|e| Error::InvalidValue {
+        ty: "Vec<EventRecord>".to_string(),
+        value: format!("{:?}", e),
+    })?;
+    decode_events(metadata, registry, &mut bytes.as_slice())
+}
+
+/// Decodes a SCALE encoded `Vec<EventRecord>`.
+pub fn decode_events<I: Input>(
+    metadata: &NodeMetadata,
+    registry: &TypeRegistry,
+    input: &mut I,
+) -> Result<Vec<EventRecord>, Error> {
+    let len = <Compact<u32>>::decode(input).map_err(invalid("Vec<EventRecord>"))?.0;
+    (0..len).map(|_| decode_event_record(metadata, registry, input)).collect()
+}
+
+fn decode_event_record<I: Input>(
+    metadata: &NodeMetadata,
+    registry: &TypeRegistry,
+    input: &mut I,
+) -> Result<EventRecord, Error> {
+    let phase = match input.read_byte().map_err(invalid("Phase"))? {
+        0 => Phase::ApplyExtrinsic(u32::decode(input).map_err(invalid("Phase"))?),
+        1 => Phase::Finalization,
+        i => {
+            return Err(Error::InvalidValue { ty: "Phase".to_string(), value: format!("variant index {}", i) })
+        }
+    };
+
+    let module_index = input.read_byte().map_err(invalid("Event"))?;
+    let event_index = input.read_byte().map_err(invalid("Event"))?;
+    let (module, event) = event_modules(metadata)
+        .nth(module_index as usize)
+        .and_then(|m| m.events.get(event_index as usize).map(|e| (m, e)))
+        .ok_or(Error::EventNotFound(module_index, event_index))?;
+
+    let mut recorder = Recorder { input: &mut *input, bytes: Vec::new() };
+    let mut args = Vec::new();
+    for ty in &event.args {
+        args.push(decode_bytes(ty, registry, &mut recorder)?);
+    }
+    let raw_args = recorder.bytes;
+    let topics = Vec::<Hash>::decode(input).map_err(invalid("Vec<Hash>"))?;
+
+    Ok(EventRecord { phase, module: module.name.clone(), name: event.name.clone(), args, raw_args, topics })
+}
+
+/// Passes reads through to `input` and keeps a copy of the bytes read.
+struct Recorder<'a, I: Input> {
+    input: &'a mut I,
+    bytes: Vec<u8>,
+}
+
+impl<'a, I: Input> Input for Recorder<'a, I> {
+    fn remaining_len(&mut self) -> Result<Option<usize>, codec::Error> {
+        self.input.remaining_len()
+    }
+
+    fn read(&mut self, into: &mut [u8]) -> Result<(), codec::Error> {
+        self.input.read(into)?;
+        self.bytes.extend_from_slice(into);
+        Ok(())
+    }
+}
+
+/// The modules in the order of the runtime's event enum, i.e. counting only modules with events.
+fn event_modules(metadata: &NodeMetadata) -> impl Iterator<Item = &Module> {
+    metadata.iter().filter(|m| !m.events.is_empty())
+}
+
+fn invalid(ty: &'static str) -> impl Fn(codec::Error) -> Error {
+    move |e| Error::InvalidValue { ty: ty.to_string(), value: format!("{:?}", e) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node_metadata::Event;
+    use codec::Encode;
+
+    fn metadata() -> NodeMetadata {
+        let event = |name: &str, args: &[&str]| Event {
+            name: name.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+        };
+        vec![
+            Module {
+                name: "System".to_string(),
+                events: vec![
+                    event("ExtrinsicSuccess", &["DispatchInfo"]),
+                    event("ExtrinsicFailed", &["DispatchError", "DispatchInfo"]),
+                ],
+                ..Default::default()
+            },
+            Module { name: "Timestamp".to_string(), ..Default::default() },
+            Module {
+                name: "Balances".to_string(),
+                events: vec![event("NewAccount", &["AccountId", "Balance"]), event("Transfer", &["AccountId", "AccountId", "Balance", "Balance"])],
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn test_decode_events() {
+        let mut bytes = Compact(2u32).encode();
+        // ApplyExtrinsic(1), Balances::NewAccount, no topics
+        bytes.extend(vec![0, 1, 0, 0, 0, 1, 0]);
+        bytes.extend(([1u8; 32], 500u128).encode());
+        bytes.extend(Vec::<Hash>::new().encode());
+        // Finalization, System::ExtrinsicFailed, one topic
+        bytes.extend(vec![1, 0, 1]);
+        bytes.extend((Some(3u8), 2u8).encode());
+        bytes.extend((10u32, 1u8, true).encode());
+        bytes.extend(vec![Hash::repeat_byte(7)].encode());
+
+        let events = decode_events_hexstr(&metadata(), &TypeRegistry::new(), format!("\"0x{}\"", hex::encode(bytes))).unwrap();
+        assert_eq!(
+            events[0],
+            EventRecord {
+                phase: Phase::ApplyExtrinsic(1),
+                module: "Balances".to_string(),
+                name: "NewAccount".to_string(),
+                args: vec![Value::Bytes(vec![1u8; 32]), Value::Uint(500)],
+                raw_args: ([1u8; 32], 500u128).encode(),
+                topics: vec![],
+            }
+        );
+        assert_eq!(events[0].decode_args::<([u8; 32], u128)>(), Ok(([1u8; 32], 500)));
+        assert_eq!(events[1].phase, Phase::Finalization);
+        assert_eq!((events[1].module.as_str(), events[1].name.as_str()), ("System", "ExtrinsicFailed"));
+        assert_eq!(
+            events[1].args[0],
+            Value::Struct(vec![
+                ("module".to_string(), Value::Option(Some(Box::new(Value::Uint(3))))),
+                ("error".to_string(), Value::Uint(2)),
+            ])
+        );
+        assert_eq!(
+            events[1].args[1],
+            Value::Struct(vec![
+                ("weight".to_string(), Value::Uint(10)),
+                ("class".to_string(), Value::Enum { name: "Operational".to_string(), fields: vec![] }),
+                ("pays_fee".to_string(), Value::Bool(true)),
+            ])
+        );
+        assert_eq!(events[1].topics, vec![Hash::repeat_byte(7)]);
+    }
+
+    #[test]
+    fn test_decode_session_and_grandpa_events() {
+        let event = |name: &str, arg: &str| Event { name: name.to_string(), args: vec![arg.to_string()] };
+        let metadata = vec![
+            Module { name: "Session".to_string(), events: vec![event("NewSession", "SessionIndex")], ..Default::default() },
+            Module {
+                name: "Grandpa".to_string(),
+                events: vec![event("NewAuthorities", "AuthorityList")],
+                ..Default::default()
+            },
+        ];
+
+        let mut bytes = Compact(2u32).encode();
+        // Finalization, Session::NewSession(7), no topics
+        bytes.extend(vec![1, 0, 0]);
+        bytes.extend(7u32.encode());
+        bytes.extend(Vec::<Hash>::new().encode());
+        // Finalization, Grandpa::NewAuthorities with two authorities, no topics
+        bytes.extend(vec![1, 1, 0]);
+        bytes.extend(vec![([1u8; 32], 1u64), ([2u8; 32], 1u64)].encode());
+        bytes.extend(Vec::<Hash>::new().encode());
+
+        let events = decode_events(&metadata, &TypeRegistry::new(), &mut bytes.as_slice()).unwrap();
+        assert_eq!((events[0].module.as_str(), events[0].name.as_str()), ("Session", "NewSession"));
+        assert_eq!(events[0].args, vec![Value::Uint(7)]);
+        assert_eq!((events[1].module.as_str(), events[1].name.as_str()), ("Grandpa", "NewAuthorities"));
+        assert_eq!(events[1].decode_args::<Vec<([u8; 32], u64)>>(), Ok(vec![([1u8; 32], 1), ([2u8; 32], 1)]));
+    }
+
+    #[test]
+    fn test_dispatch_result() {
+        let record = |name: &str, args: Vec<Value>| EventRecord {
+            phase: Phase::ApplyExtrinsic(0),
+            module: "System".to_string(),
+            name: name.to_string(),
+            args,
+            raw_args: vec![],
+            topics: vec![],
+        };
+        let error = Value::Struct(vec![
+            ("module".to_string(), Value::Option(Some(Box::new(Value::Uint(2))))),
+            ("error".to_string(), Value::Uint(0)),
+        ]);
+
+        assert_eq!(dispatch_result(&[]), None);
+        assert_eq!(dispatch_result(&[record("ExtrinsicSuccess", vec![])]), Some(Ok(())));
+        let result = dispatch_result(&[record("ExtrinsicFailed", vec![error])]);
+        let error = DispatchError::Error { module: Some(2), error: 0 };
+        assert_eq!(result, Some(Err(error.clone())));
+        assert_eq!(
+            dispatch_result(&[EventRecord { raw_args: vec![7], ..record("ExtrinsicFailed", vec![]) }]),
+            Some(Err(DispatchError::Undecodable(vec![7])))
+        );
+
+        let mut metadata = metadata();
+        metadata[2].errors = vec!["InsufficientBalance".to_string()];
+        assert_eq!(error.describe(&metadata), Some(("Balances".to_string(), "InsufficientBalance".to_string())));
+        assert_eq!(DispatchError::Error { module: None, error: 1 }.describe(&metadata), None);
+        assert_eq!(DispatchError::Undecodable(vec![]).describe(&metadata), None);
+    }
+
+    #[test]
+    fn test_event_filter() {
+        let record = |module: &str, name: &str| EventRecord {
+            phase: Phase::Finalization,
+            module: module.to_string(),
+            name: name.to_string(),
+            args: vec![],
+            raw_args: vec![],
+            topics: vec![],
+        };
+        let filter = EventFilter::new().module("BridgeEOS").event("Balances", "Transfer");
+
+        assert!(EventFilter::new().matches(&record("System", "ExtrinsicSuccess")));
+        assert!(filter.matches(&record("BridgeEOS", "ProveAction")));
+        assert!(filter.matches(&record("Balances", "Transfer")));
+        assert!(!filter.matches(&record("Balances", "NewAccount")));
+        assert!(!filter.matches(&record("System", "ExtrinsicSuccess")));
+    }
+
+    #[test]
+    fn test_decode_unknown_event() {
+        let mut bytes = Compact(1u32).encode();
+        bytes.extend(vec![1, 1, 5]);
+        assert_eq!(
+            decode_events(&metadata(), &TypeRegistry::new(), &mut bytes.as_slice()),
+            Err(Error::EventNotFound(1, 5))
+        );
+    }
+}
@@ -16,6 +16,7 @@
 #[cfg(feature = "std")]
 pub extern crate codec;
 pub extern crate node_primitives;
+pub extern crate primitives;
 
 #[macro_use]
 pub mod xt_macros;
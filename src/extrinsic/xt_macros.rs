@@ -284,8 +284,8 @@ mod tests {
 
         println!("[+] Composed extrinsic: {:?}\n", xt);
         // send and watch extrinsic until finalized
-        let tx_hash = api.send_extrinsic(xt.hex_encode()).unwrap();
-        println!("[+] Transaction got finalized. Hash: {:?}\n", tx_hash);
+        let report = api.send_extrinsic(xt.hex_encode()).unwrap();
+        println!("[+] Transaction got finalized. Hash: {:?}\n", report.block_hash);
     }
 
     #[test]
@@ -402,8 +402,8 @@ mod tests {
 
         println!("[+] Composed extrinsic: {:?}\n", xt);
         // send and watch extrinsic until finalized
-        let tx_hash = api.send_extrinsic(xt.hex_encode()).unwrap();
-        println!("[+] Transaction got finalized. Hash: {:?}\n", tx_hash);
+        let report = api.send_extrinsic(xt.hex_encode()).unwrap();
+        println!("[+] Transaction got finalized. Hash: {:?}\n", report.block_hash);
     }
 
     #[test]
@@ -458,8 +458,8 @@ mod tests {
         // Unable to decode Vec on index 2 createType(ExtrinsicV4):: Source is too large
         println!("[+] Composed extrinsic: {:?}\n", xt);
         // send and watch extrinsic until finalized
-        let tx_hash = api.send_extrinsic(xt.hex_encode()).unwrap();
-        println!("[+] Transaction got finalized. Hash: {:?}\n", tx_hash);
+        let report = api.send_extrinsic(xt.hex_encode()).unwrap();
+        println!("[+] Transaction got finalized. Hash: {:?}\n", report.block_hash);
     }
 
     fn read_json_from_file(json_name: impl AsRef<str>) -> Result<String, Box<dyn Error>> {
@@ -479,7 +479,7 @@ mod tests {
         let api = Api::new(format!("ws://{}", url)).set_signer(from.clone());
 
         let to = AccountId::from(AccountKeyring::Bob);
-        let result = api.get_free_balance(&to);
+        let result = api.get_free_balance(&to).unwrap();
         info!("[+] Bob's Free Balance is is {}\n", result);
 
         let acc_id = GenericAddress::from(to.clone());
@@ -500,11 +500,12 @@ mod tests {
 
         println!("[+] Composed extrinsic: {:?}\n", xt);
         // send and watch extrinsic until finalized
-        let tx_hash = api.send_extrinsic(xt.hex_encode()).unwrap();
-        println!("[+] Transaction got finalized. Hash: {:?}\n", tx_hash);
+        let report = api.send_extrinsic(xt.hex_encode()).unwrap();
+        println!("[+] Transaction got finalized. Hash: {:?}\n", report.block_hash);
+        assert_eq!(report.result, Some(Ok(())));
 
         // verify that Bob's free Balance increased
-        let result = api.get_free_balance(&to);
+        let result = api.get_free_balance(&to).unwrap();
         println!("[+] Bob's Free Balance is now {}\n", result);
     }
 }
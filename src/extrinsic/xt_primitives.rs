@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with Bifrost.  If not, see <http://www.gnu.org/licenses/>.
 
-use codec::{Compact, Decode, Encode};
+use codec::{Compact, Decode, Encode, Error, Input, Output};
 use indices::address::Address;
 use node_primitives::{AccountIndex, AccountId};
 use primitive_types::H256;
@@ -144,6 +144,83 @@ impl<Call> Encode for UncheckedExtrinsicV4<Call>
     }
 }
 
+impl<Call> Decode for UncheckedExtrinsicV4<Call>
+    where
+        Call: Decode + Encode,
+{
+    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        // the extrinsic is prefixed with its length, like a Vec<u8>, see `encode`
+        let length: Compact<u32> = Decode::decode(input)?;
+        let bytes = read_bytes(input, length.0 as usize)?;
+        let mut input = bytes.as_slice();
+
+        let version = input.read_byte()?;
+        let is_signed = version & 0b1000_0000 != 0;
+        if version & 0b0111_1111 != 4 {
+            return Err("Invalid transaction version".into());
+        }
+
+        let xt = UncheckedExtrinsicV4 {
+            signature: if is_signed { Some(Decode::decode(&mut input)?) } else { None },
+            function: Decode::decode(&mut input)?,
+        };
+        if !input.is_empty() {
+            return Err("Extrinsic longer than its call".into());
+        }
+        Ok(xt)
+    }
+}
+
+/// A call of any module: module index, call index and the encoded arguments. Decoding takes
+/// all remaining input as arguments, so decode it from the bytes of a single extrinsic only,
+/// as `UncheckedExtrinsicV4` does.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Eq, PartialEq)]
+pub struct GenericCall {
+    pub module_index: u8,
+    pub call_index: u8,
+    pub args: Vec<u8>,
+}
+
+impl Encode for GenericCall {
+    fn encode_to<T: Output>(&self, dest: &mut T) {
+        dest.push_byte(self.module_index);
+        dest.push_byte(self.call_index);
+        dest.write(&self.args);
+    }
+}
+
+impl Decode for GenericCall {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        let module_index = input.read_byte()?;
+        let call_index = input.read_byte()?;
+        let len = input
+            .remaining_len()?
+            .ok_or_else(|| Error::from("Call arguments of unknown length"))?;
+        let args = read_bytes(input, len)?;
+        Ok(GenericCall { module_index, call_index, args })
+    }
+}
+
+/// Reads `len` bytes. `len` usually comes from the input itself, so it is checked against the
+/// remaining input, or the bytes are read in chunks, before allocating that much.
+pub fn read_bytes<I: Input>(input: &mut I, len: usize) -> Result<Vec<u8>, Error> {
+    const CHUNK: usize = 16 * 1024;
+
+    if let Some(remaining) = input.remaining_len()? {
+        if remaining < len {
+            return Err("Not enough data to fill buffer".into());
+        }
+    }
+    let mut bytes = Vec::new();
+    while bytes.len() < len {
+        let start = bytes.len();
+        bytes.resize(len.min(start + CHUNK), 0);
+        input.read(&mut bytes[start..])?;
+    }
+    Ok(bytes)
+}
+
 /// Same function as in primitives::generic. Needed to be copied as it is private there.
 fn encode_with_vec_prefix<T: Encode, F: Fn(&mut Vec<u8>)>(encoder: F) -> Vec<u8> {
     let size = rstd::mem::size_of::<T>();
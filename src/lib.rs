@@ -19,9 +19,15 @@
 use rstd::prelude::*;
 
 #[cfg(feature = "std")]
-use std::sync::mpsc::channel;
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::sync::mpsc::{channel, Receiver};
 #[cfg(feature = "std")]
 use std::sync::mpsc::Sender as ThreadOut;
+#[cfg(feature = "std")]
+use std::thread;
+#[cfg(feature = "std")]
+use std::time::Duration;
 
 use codec::{Decode, Encode};
 
@@ -34,9 +40,9 @@ use primitives::H256 as Hash;
 use primitives::crypto::Pair;
 
 #[cfg(feature = "std")]
-use websocket::ClientBuilder;
+pub use ws::Result as WsResult;
 #[cfg(feature = "std")]
-use ws::Result as WsResult;
+use ws::{Error as WsError, ErrorKind};
 
 #[cfg(feature = "std")]
 use node_metadata::NodeMetadata;
@@ -48,12 +54,30 @@ use rpc::json_req;
 use utils::*;
 
 use primitive_types::U256;
+#[cfg(feature = "std")]
+use node_primitives::Balance;
 use runtime_version::RuntimeVersion;
 
 #[macro_use]
 pub mod extrinsic;
 #[cfg(feature = "std")]
 pub mod node_metadata;
+#[cfg(feature = "std")]
+pub mod metadata_diff;
+#[cfg(feature = "std")]
+pub mod codegen;
+#[cfg(feature = "std")]
+pub mod dynamic;
+#[cfg(feature = "std")]
+pub mod proof;
+#[cfg(feature = "std")]
+pub mod events;
+#[cfg(feature = "std")]
+pub mod chain;
+#[cfg(feature = "std")]
+pub mod system;
+#[cfg(feature = "std")]
+pub mod payment;
 
 #[cfg(feature = "std")]
 pub mod utils;
@@ -62,6 +86,80 @@ pub mod rpc;
 
 use runtime_primitives::{AccountId32, MultiSignature};
 
+#[cfg(feature = "std")]
+use dynamic::{RawCall, TypeRegistry};
+#[cfg(feature = "std")]
+use extrinsic::xt_primitives::UncheckedExtrinsicV4;
+#[cfg(feature = "std")]
+use proof::ReadProof;
+#[cfg(feature = "std")]
+use events::{DispatchError, EventFilter, EventRecord, Phase};
+#[cfg(feature = "std")]
+use chain::{Block, BlockExtrinsic, Header, RawExtrinsic, SignedBlockJson};
+#[cfg(feature = "std")]
+use system::{Health, PeerInfo, Properties};
+#[cfg(feature = "std")]
+use payment::FeeInfo;
+#[cfg(feature = "std")]
+pub use rpc::json_req::ExtrinsicOrHash;
+
+/// How long `is_online` waits for the node to answer.
+#[cfg(feature = "std")]
+pub const IS_ONLINE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Sent to the application when the node's `spec_version` changed. Carries the new runtime
+/// version together with the freshly parsed metadata, see `Api::watch_runtime_upgrades`.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct RuntimeUpgrade {
+    pub runtime_version: RuntimeVersion,
+    pub metadata: NodeMetadata,
+}
+
+/// A notification of a storage subscription: the values of the subscribed keys that changed
+/// in `block`. A `None` value means the key was removed.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct StorageChangeSet<V> {
+    pub block: Hash,
+    pub changes: Vec<(String, Option<V>)>,
+}
+
+/// The events of one block, see `Api::scan_events`.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockEvents {
+    pub number: u32,
+    pub hash: Hash,
+    pub events: Vec<EventRecord>,
+}
+
+/// What became of an extrinsic sent with `Api::send_extrinsic`.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtrinsicReport {
+    /// The finalized block the extrinsic was included in.
+    pub block_hash: Hash,
+    /// `None` if the block couldn't be fetched or doesn't list the extrinsic.
+    pub extrinsic_index: Option<u32>,
+    /// The events emitted while applying the extrinsic, or why they couldn't be collected.
+    pub events: Result<Vec<EventRecord>, String>,
+    /// Whether the call succeeded, see `DispatchError::describe` for failures. `None` if the
+    /// events are unknown or contain neither `ExtrinsicSuccess` nor `ExtrinsicFailed`.
+    pub result: Option<Result<(), DispatchError>>,
+}
+
+/// An entry of a storage map, see `Api::get_storage_map_entries`. `decoded_key` is only
+/// available if the map's hasher allows recovering the key, i.e. for `Blake2_128Concat`,
+/// `Twox64Concat` and `Identity`.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapEntry<K, V> {
+    pub key: String,
+    pub decoded_key: Option<K>,
+    pub value: V,
+}
+
 #[cfg(feature = "std")]
 #[derive(Clone)]
 pub struct Api<P>
@@ -74,6 +172,12 @@ pub struct Api<P>
     pub genesis_hash: Hash,
     pub metadata: NodeMetadata,
     pub runtime_version: RuntimeVersion,
+    /// Custom types consulted when encoding and decoding dynamically.
+    pub types: TypeRegistry,
+    /// Layout of the storage keys of the connected runtime.
+    pub storage_layout: StorageLayout,
+    /// Whether typed storage reads return the metadata default for absent items declared `Default`.
+    pub storage_defaults: bool,
 }
 
 #[cfg(feature = "std")]
@@ -99,6 +203,9 @@ impl<P> Api<P>
             genesis_hash,
             metadata,
             runtime_version,
+            types: TypeRegistry::new(),
+            storage_layout: StorageLayout::default(),
+            storage_defaults: false,
         }
     }
 
@@ -107,72 +214,137 @@ impl<P> Api<P>
         self
     }
 
+    pub fn set_types(mut self, types: TypeRegistry) -> Self {
+        self.types = types;
+        self
+    }
+
+    pub fn set_storage_layout(mut self, storage_layout: StorageLayout) -> Self {
+        self.storage_layout = storage_layout;
+        self
+    }
+
+    pub fn set_storage_defaults(mut self, storage_defaults: bool) -> Self {
+        self.storage_defaults = storage_defaults;
+        self
+    }
+
     fn _get_genesis_hash(url: String) -> Hash {
-        let jsonreq = json_req::chain_get_block_hash();
+        let jsonreq = json_req::chain_get_block_hash(Some(0));
         let genesis_hash_str = Self::_get_request(url, jsonreq.to_string())
             .expect("Fetching genesis hash from node failed");
         hexstr_to_hash(genesis_hash_str).unwrap()
     }
 
     fn _get_runtime_version(url: String) -> RuntimeVersion {
-        let jsonreq = json_req::state_get_runtime_version();
-        let version_str = Self::_get_request(url, jsonreq.to_string()).unwrap(); //expect("Fetching runtime version from node failed");
+        Self::_fetch_runtime_version(url, None).unwrap() //expect("Fetching runtime version from node failed");
+    }
+
+    fn _fetch_runtime_version(url: String, at: Option<Hash>) -> WsResult<RuntimeVersion> {
+        let jsonreq = json_req::state_get_runtime_version(at);
+        let version_str = Self::_get_request(url, jsonreq.to_string())?;
         debug!("got the following runtime version (raw): {}", version_str);
-        serde_json::from_str(&version_str).unwrap()
+        parse_result(&version_str)
     }
 
     fn _get_metadata(url: String) -> RuntimeMetadataPrefixed {
-        let jsonreq = json_req::state_get_metadata();
-        let metadata_str = Self::_get_request(url, jsonreq.to_string()).unwrap();
+        Self::_fetch_metadata(url, None).unwrap()
+    }
 
-        let _unhex = hexstr_to_vec(metadata_str).unwrap();
+    fn _fetch_metadata(url: String, at: Option<Hash>) -> WsResult<RuntimeMetadataPrefixed> {
+        let jsonreq = json_req::state_get_metadata(at);
+        let metadata_str = Self::_get_request(url, jsonreq.to_string())?;
+
+        let _unhex = hexstr_to_vec(metadata_str)
+            .map_err(|e| WsError::new(ErrorKind::Protocol, format!("invalid metadata: {:?}", e)))?;
         let mut _om = _unhex.as_slice();
-        RuntimeMetadataPrefixed::decode(&mut _om).unwrap()
+        RuntimeMetadataPrefixed::decode(&mut _om)
+            .map_err(|e| WsError::new(ErrorKind::Protocol, format!("could not decode metadata: {:?}", e)))
     }
 
-    fn _get_nonce(url: String, signer: [u8; 32]) -> u32 {
-        let result_str = Self::_get_storage(
-            url,
-            "System",
-            "AccountNonce",
-            Some(signer.encode()),
-        )
-            .unwrap();
-        let nonce = hexstr_to_u256(result_str).unwrap_or(U256::from_little_endian(&[0, 0, 0, 0]));
-        nonce.low_u32()
+    fn _get_storage(url: String, keyhash: &str, at: Option<Hash>) -> WsResult<String> {
+        debug!("with storage key: {} at block {:?}", keyhash, at);
+        let jsonreq = json_req::state_get_storage(keyhash, at);
+        Self::_get_request(url, jsonreq.to_string())
     }
 
-    fn _get_storage(
-        url: String,
-        module: &str,
-        storage_key_name: &str,
-        param: Option<Vec<u8>>,
-    ) -> WsResult<String> {
-        let keyhash = storage_key_hash(module, storage_key_name, param);
-        debug!("with storage key: {}", keyhash);
-        let jsonreq = json_req::state_get_storage(&keyhash);
-        Self::_get_request(url, jsonreq.to_string())
+    fn _get_header(url: String, at: Option<Hash>) -> WsResult<Option<Header>> {
+        let jsonreq = json_req::chain_get_header(at);
+        parse_result(&Self::_get_request(url, jsonreq.to_string())?)
     }
 
-    fn _get_storage_double_map(
-        url: String,
+    /// Fetches and decodes a storage value at block `at`, or at the best block. Absent keys
+    /// yield `None`, or the metadata default if `storage_defaults` is set. Node errors and
+    /// undecodable values are returned as errors.
+    fn _get_storage_decoded<V: Decode>(
+        &self,
         module: &str,
         storage_key_name: &str,
-        first: Vec<u8>,
-        second: Vec<u8>
-    ) -> WsResult<String> {
-        let keyhash = storage_key_hash_double_map(module, storage_key_name, first, second);
-        debug!("with storage key: {}", keyhash);
-        let jsonreq = json_req::state_get_storage(&keyhash);
-        Self::_get_request(url, jsonreq.to_string())
+        keyhash: &str,
+        at: Option<Hash>,
+    ) -> WsResult<Option<V>> {
+        let hexstr = Self::_get_storage(self.url.clone(), keyhash, at)?;
+        let bytes = match hexstr_to_opt_vec(hexstr)
+            .map_err(|e| WsError::new(ErrorKind::Protocol, format!("invalid storage value: {:?}", e)))?
+        {
+            Some(bytes) => bytes,
+            None if self.storage_defaults => {
+                match node_metadata::find_storage(&self.metadata, module, storage_key_name)
+                    .and_then(|(_, s)| s.default.clone())
+                {
+                    Some(default) => default,
+                    None => return Ok(None),
+                }
+            }
+            None => return Ok(None),
+        };
+        V::decode(&mut bytes.as_slice())
+            .map(Some)
+            .map_err(|e| WsError::new(ErrorKind::Protocol, format!("could not decode storage value: {:?}", e)))
     }
 
     // low level access
     fn _get_request(url: String, jsonreq: String) -> WsResult<String> {
         let (result_in, result_out) = channel();
-        rpc::get(url, jsonreq.clone(), result_in.clone());
+        rpc::get(url, jsonreq, result_in);
 
-        Ok(result_out.recv().unwrap())
+        let response = result_out
+            .recv()
+            .map_err(|_| WsError::new(ErrorKind::Internal, "rpc client thread hung up without a response"))?;
+        let value: serde_json::Value = serde_json::from_str(&response)
+            .map_err(|e| WsError::new(ErrorKind::Protocol, format!("invalid json response: {}", e)))?;
+        match value.get("error") {
+            Some(err) => Err(WsError::new(ErrorKind::Protocol, err.to_string())),
+            None => Ok(value["result"].to_string()),
+        }
+    }
+
+    /// Sends `jsonreqs` as one JSON-RPC batch and returns their results in the same order.
+    /// The requests need distinct ids.
+    fn _get_request_batch(url: String, jsonreqs: Vec<serde_json::Value>) -> WsResult<Vec<String>> {
+        if jsonreqs.is_empty() {
+            return Ok(vec![]);
+        }
+        let (result_in, result_out) = channel();
+        rpc::get(url, serde_json::Value::Array(jsonreqs.clone()).to_string(), result_in);
+
+        let response = result_out
+            .recv()
+            .map_err(|_| WsError::new(ErrorKind::Internal, "rpc client thread hung up without a response"))?;
+        let responses: Vec<serde_json::Value> = serde_json::from_str(&response)
+            .map_err(|e| WsError::new(ErrorKind::Protocol, format!("invalid batch response: {}", e)))?;
+        jsonreqs
+            .iter()
+            .map(|req| {
+                let value = responses.iter().find(|r| r["id"] == req["id"]).ok_or_else(|| {
+                    WsError::new(ErrorKind::Protocol, format!("no response to request {}", req["id"]))
+                })?;
+                match value.get("error") {
+                    Some(err) => Err(WsError::new(ErrorKind::Protocol, err.to_string())),
+                    None => Ok(value["result"].to_string()),
+                }
+            })
+            .collect()
     }
 
     pub fn get_metadata(&self) -> RuntimeMetadataPrefixed {
@@ -188,77 +360,895 @@ impl<P> Api<P>
     }
 
     pub fn get_nonce(&self) -> Result<u32, &str> {
+        self.get_nonce_at(None)
+    }
+
+    /// The signer's nonce as of block `at`, or at the best block if `at` is `None`.
+    pub fn get_nonce_at(&self, at: Option<Hash>) -> Result<u32, &str> {
         match &self.signer {
             Some(key) => {
                 let mut arr: [u8; 32] = Default::default();
                 arr.clone_from_slice(key.to_owned().public().as_ref());
-                Ok(Self::_get_nonce(self.url.clone(), arr))
+                match self.get_storage_map::<_, u32>("System", "AccountNonce", arr, at) {
+                    // accounts without any extrinsic have no nonce stored
+                    Ok(nonce) => Ok(nonce.unwrap_or(0)),
+                    Err(e) => {
+                        error!("Fetching nonce from node failed: {:?}", e);
+                        Err("Fetching nonce from node failed")
+                    }
+                }
             },
             None => Err("Can't get nonce when no signer is set"),
         }
     }
 
-    pub fn get_free_balance(&self, address: &AccountId32) -> U256 {
+    pub fn get_free_balance(&self, address: &AccountId32) -> WsResult<Balance> {
+        self.get_free_balance_at(address, None)
+    }
+
+    /// The free balance of `address` as of block `at`, or at the best block if `at` is `None`.
+    pub fn get_free_balance_at(&self, address: &AccountId32, at: Option<Hash>) -> WsResult<Balance> {
         let id: &[u8; 32] = address.as_ref();
-        let result_str = self
-            .get_storage("Balances", "FreeBalance", Some(id.to_owned().encode()))
-            .unwrap();
-        hexstr_to_u256(result_str).unwrap()
+        let balance = self.get_storage_map::<_, Balance>("Balances", "FreeBalance", id.to_owned(), at)?;
+        Ok(balance.unwrap_or(0))
     }
 
     pub fn get_request(&self, jsonreq: String) -> WsResult<String> {
         Self::_get_request(self.url.clone(), jsonreq)
     }
 
+    /// Whether the node answers requests, see the free function `is_online`.
+    pub fn is_online(&self) -> bool {
+        is_online(&self.url)
+    }
+
+    pub fn get_health(&self) -> WsResult<Health> {
+        parse_result(&self.get_request(json_req::system_health().to_string())?)
+    }
+
+    pub fn get_peers(&self) -> WsResult<Vec<PeerInfo>> {
+        parse_result(&self.get_request(json_req::system_peers().to_string())?)
+    }
+
+    /// The name of the node implementation.
+    pub fn get_node_name(&self) -> WsResult<String> {
+        parse_result(&self.get_request(json_req::system_name().to_string())?)
+    }
+
+    /// The version of the node implementation.
+    pub fn get_node_version(&self) -> WsResult<String> {
+        parse_result(&self.get_request(json_req::system_version().to_string())?)
+    }
+
+    /// The name of the chain, as given in its chain spec.
+    pub fn get_chain_name(&self) -> WsResult<String> {
+        parse_result(&self.get_request(json_req::system_chain().to_string())?)
+    }
+
+    /// The token symbol, decimals and SS58 prefix of the chain.
+    pub fn get_properties(&self) -> WsResult<Properties> {
+        parse_result(&self.get_request(json_req::system_properties().to_string())?)
+    }
+
+    /// Builds the storage key of `module::storage_key_name` for the given encoded map keys.
+    /// The hashers and the storage prefix are taken from the metadata; items missing from it
+    /// fall back to blake2_256.
+    pub fn storage_key(&self, module: &str, storage_key_name: &str, keys: Vec<Vec<u8>>) -> String {
+        let (prefix, hashers) = self.storage_hashers(module, storage_key_name);
+        storage_key_hex(self.storage_layout, prefix, storage_key_name, &with_hashers(&hashers, keys))
+    }
+
+    /// The hex encoded prefix shared by all keys of a map, or of a double map under the given
+    /// first key. `None` for maps in the legacy layout, which can't be enumerated.
+    pub fn storage_key_prefix(&self, module: &str, storage_key_name: &str, keys: Vec<Vec<u8>>) -> Option<String> {
+        let (prefix, hashers) = self.storage_hashers(module, storage_key_name);
+        storage_key_prefix(self.storage_layout, prefix, storage_key_name, &with_hashers(&hashers, keys))
+            .map(|prefix| format!("0x{}", hex::encode(prefix)))
+    }
+
+    fn storage_hashers<'a>(&'a self, module: &'a str, storage_key_name: &str) -> (&'a str, Vec<StorageHasher>) {
+        match node_metadata::find_storage(&self.metadata, module, storage_key_name) {
+            Some((m, s)) if !m.storage_prefix.is_empty() => (m.storage_prefix.as_str(), s.ty.hashers()),
+            Some((_, s)) => (module, s.ty.hashers()),
+            None => (module, vec![]),
+        }
+    }
+
+    /// All storage keys starting with `prefix`, fetched with `state_getKeysPaged` in pages of
+    /// `page_size` keys. Falls back to `state_getKeys` on nodes without paging.
+    pub fn get_storage_keys(&self, prefix: &str, page_size: u32, at: Option<Hash>) -> WsResult<Vec<String>> {
+        match self._get_storage_keys_paged(prefix, page_size, at) {
+            Err(ref e) if is_method_not_found(e) => {
+                debug!("state_getKeysPaged is not supported, falling back to state_getKeys");
+                let jsonreq = json_req::state_get_keys(prefix, at);
+                parse_result(&self.get_request(jsonreq.to_string())?)
+            }
+            result => result,
+        }
+    }
+
+    fn _get_storage_keys_paged(&self, prefix: &str, page_size: u32, at: Option<Hash>) -> WsResult<Vec<String>> {
+        let page_size = page_size.max(1);
+        let mut keys: Vec<String> = Vec::new();
+        loop {
+            let start_key = keys.last().map(String::as_str);
+            let jsonreq = json_req::state_get_keys_paged(prefix, page_size, start_key, at);
+            let page: Vec<String> = parse_result(&self.get_request(jsonreq.to_string())?)?;
+            let last_page = (page.len() as u32) < page_size;
+            keys.extend(page);
+            if last_page {
+                return Ok(keys);
+            }
+        }
+    }
+
+    /// All keys starting with `prefix` together with their encoded values. Falls back to
+    /// `state_getPairs` on nodes without `state_getKeysPaged`.
+    fn _get_storage_pairs(&self, prefix: &str, page_size: u32, at: Option<Hash>) -> WsResult<Vec<(String, Vec<u8>)>> {
+        let invalid = |e: hex::FromHexError| WsError::new(ErrorKind::Protocol, format!("invalid storage value: {:?}", e));
+        match self._get_storage_keys_paged(prefix, page_size, at) {
+            Ok(keys) => {
+                let mut pairs = Vec::new();
+                // one batch request per page, over a single connection each
+                for page in keys.chunks(page_size.max(1) as usize) {
+                    let jsonreqs = page
+                        .iter()
+                        .enumerate()
+                        .map(|(id, key)| json_req::state_get_storage_with_id(key, at, id as u32))
+                        .collect();
+                    let values = Self::_get_request_batch(self.url.clone(), jsonreqs)?;
+                    for (key, value) in page.iter().zip(values) {
+                        // the key may have been removed since it was listed
+                        if let Some(value) = hexstr_to_opt_vec(value).map_err(invalid)? {
+                            pairs.push((key.clone(), value));
+                        }
+                    }
+                }
+                Ok(pairs)
+            }
+            Err(ref e) if is_method_not_found(e) => {
+                debug!("state_getKeysPaged is not supported, falling back to state_getPairs");
+                let jsonreq = json_req::state_get_pairs(prefix, at);
+                let pairs: Vec<(String, String)> = parse_result(&self.get_request(jsonreq.to_string())?)?;
+                pairs
+                    .into_iter()
+                    .map(|(key, value)| hexstr_to_vec(value).map(|value| (key, value)).map_err(invalid))
+                    .collect()
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn _get_storage_entries<K: Decode, V: Decode>(
+        &self,
+        module: &str,
+        storage_key_name: &str,
+        keys: Vec<Vec<u8>>,
+        page_size: u32,
+        at: Option<Hash>,
+    ) -> WsResult<Vec<MapEntry<K, V>>> {
+        let prefix = self.storage_key_prefix(module, storage_key_name, keys.clone()).ok_or_else(|| {
+            WsError::new(
+                ErrorKind::Internal,
+                format!("{}::{} can't be enumerated with the legacy storage layout", module, storage_key_name),
+            )
+        })?;
+        let prefix_len = (prefix.len() - 2) / 2;
+        // the hasher of the key following the prefix
+        let hasher = self.storage_hashers(module, storage_key_name).1.get(keys.len()).cloned();
+
+        let mut entries = Vec::new();
+        for (key, value) in self._get_storage_pairs(&prefix, page_size, at)? {
+            let decoded_key = match (hasher, hexstr_to_vec(key.clone())) {
+                (Some(hasher), Ok(bytes)) if bytes.len() >= prefix_len => hasher
+                    .reverse(&bytes[prefix_len..])
+                    .and_then(|k| K::decode(&mut &k[..]).ok()),
+                _ => None,
+            };
+            let value = V::decode(&mut value.as_slice()).map_err(|e| {
+                WsError::new(ErrorKind::Protocol, format!("could not decode storage value: {:?}", e))
+            })?;
+            entries.push(MapEntry { key, decoded_key, value });
+        }
+        Ok(entries)
+    }
+
+    /// Reads `key` from the child trie identified by `child_id`, e.g. a contract's trie id.
+    pub fn get_child_storage(&self, child_id: &[u8], key: &str, at: Option<Hash>) -> WsResult<Option<Vec<u8>>> {
+        let jsonreq = json_req::state_get_child_storage(&child_storage_key_hex(child_id), key, at);
+        hexstr_to_opt_vec(self.get_request(jsonreq.to_string())?)
+            .map_err(|e| WsError::new(ErrorKind::Protocol, format!("invalid storage value: {:?}", e)))
+    }
+
+    /// Like `get_child_storage`, but decodes the value as `V`.
+    pub fn get_child_storage_decoded<V: Decode>(
+        &self,
+        child_id: &[u8],
+        key: &str,
+        at: Option<Hash>,
+    ) -> WsResult<Option<V>> {
+        match self.get_child_storage(child_id, key, at)? {
+            Some(bytes) => V::decode(&mut bytes.as_slice()).map(Some).map_err(|e| {
+                WsError::new(ErrorKind::Protocol, format!("could not decode storage value: {:?}", e))
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// The hash of the value of `key` in the child trie identified by `child_id`.
+    pub fn get_child_storage_hash(&self, child_id: &[u8], key: &str, at: Option<Hash>) -> WsResult<Option<Hash>> {
+        let jsonreq = json_req::state_get_child_storage_hash(&child_storage_key_hex(child_id), key, at);
+        parse_result(&self.get_request(jsonreq.to_string())?)
+    }
+
+    /// The size in bytes of the value of `key` in the child trie identified by `child_id`.
+    pub fn get_child_storage_size(&self, child_id: &[u8], key: &str, at: Option<Hash>) -> WsResult<Option<u64>> {
+        let jsonreq = json_req::state_get_child_storage_size(&child_storage_key_hex(child_id), key, at);
+        parse_result(&self.get_request(jsonreq.to_string())?)
+    }
+
+    /// All keys starting with `prefix` in the child trie identified by `child_id`.
+    pub fn get_child_keys(&self, child_id: &[u8], prefix: &str, at: Option<Hash>) -> WsResult<Vec<String>> {
+        let jsonreq = json_req::state_get_child_keys(&child_storage_key_hex(child_id), prefix, at);
+        parse_result(&self.get_request(jsonreq.to_string())?)
+    }
+
+    /// The history of the storage `keys` in the blocks from `from` up to `to`, or up to the
+    /// best block. Returns one change set per block in which any of the keys changed, oldest
+    /// first; the first one holds the values at `from`.
+    pub fn query_storage(
+        &self,
+        keys: Vec<String>,
+        from: Hash,
+        to: Option<Hash>,
+    ) -> WsResult<Vec<StorageChangeSet<Vec<u8>>>> {
+        self._query_storage(keys, from, to, Ok)
+    }
+
+    /// Like `query_storage`, but decodes the values as `V`. A value that fails to decode is
+    /// kept as its decode error, so it can be told apart from a removed key (`None`).
+    pub fn query_storage_decoded<V: Decode>(
+        &self,
+        keys: Vec<String>,
+        from: Hash,
+        to: Option<Hash>,
+    ) -> WsResult<Vec<StorageChangeSet<Result<V, codec::Error>>>> {
+        self._query_storage(keys, from, to, |bytes| Ok(V::decode(&mut bytes.as_slice())))
+    }
+
+    fn _query_storage<V, F>(&self, keys: Vec<String>, from: Hash, to: Option<Hash>, decode: F) -> WsResult<Vec<StorageChangeSet<V>>>
+    where
+        F: Fn(Vec<u8>) -> Result<V, codec::Error>,
+    {
+        let jsonreq = json_req::state_query_storage(&keys, from, to);
+        let change_sets: Vec<serde_json::Value> = parse_result(&self.get_request(jsonreq.to_string())?)?;
+        change_sets
+            .iter()
+            .map(|change_set| parse_change_set(&change_set.to_string(), &decode))
+            .collect()
+    }
+
+    /// Fetches a proof of the values of `keys`, as built by `storage_key`, at block `at` or at
+    /// the best block. Verify it offline with `ReadProof::verify` against the state root of
+    /// the block, see `get_state_root`.
+    pub fn get_read_proof(&self, keys: Vec<String>, at: Option<Hash>) -> WsResult<ReadProof> {
+        let jsonreq = json_req::state_get_read_proof(&keys, at);
+        parse_result(&self.get_request(jsonreq.to_string())?)
+    }
+
+    /// The state root in the header of block `at`, or of the best block.
+    pub fn get_state_root(&self, at: Option<Hash>) -> WsResult<Hash> {
+        let jsonreq = json_req::chain_get_header(at);
+        let header: serde_json::Value = parse_result(&self.get_request(jsonreq.to_string())?)?;
+        parse_result(&header["stateRoot"].to_string())
+    }
+
+    /// The header of block `at`, or of the best block. Returns `None` for blocks the node
+    /// doesn't know.
+    pub fn get_header(&self, at: Option<Hash>) -> WsResult<Option<Header>> {
+        Self::_get_header(self.url.clone(), at)
+    }
+
+    /// Block `at`, or the best block, with its extrinsics decoded and their calls resolved
+    /// from the metadata. Returns `None` for blocks the node doesn't know. Extrinsics that
+    /// can't be decoded are returned raw.
+    pub fn get_block(&self, at: Option<Hash>) -> WsResult<Option<Block>> {
+        let jsonreq = json_req::chain_get_block(at);
+        let block: Option<SignedBlockJson> = parse_result(&self.get_request(jsonreq.to_string())?)?;
+        Ok(block.map(|b| b.block.decode(&self.metadata)))
+    }
+
+    /// Lists all entries of a storage map. Requires the prefixed storage layout.
+    pub fn get_storage_map_entries<K: Decode, V: Decode>(
+        &self,
+        module: &str,
+        storage_key_name: &str,
+        page_size: u32,
+        at: Option<Hash>,
+    ) -> WsResult<Vec<MapEntry<K, V>>> {
+        self._get_storage_entries(module, storage_key_name, vec![], page_size, at)
+    }
+
+    /// Lists all entries of a double map under the first key `first`. `decoded_key` holds the
+    /// second key.
+    pub fn get_storage_double_map_entries<K1: Encode, K2: Decode, V: Decode>(
+        &self,
+        module: &str,
+        storage_key_name: &str,
+        first: K1,
+        page_size: u32,
+        at: Option<Hash>,
+    ) -> WsResult<Vec<MapEntry<K2, V>>> {
+        self._get_storage_entries(module, storage_key_name, vec![first.encode()], page_size, at)
+    }
+
     pub fn get_storage(
         &self,
         storage_prefix: &str,
         storage_key_name: &str,
         param: Option<Vec<u8>>,
+        at: Option<Hash>,
     ) -> WsResult<String> {
-        Self::_get_storage(self.url.clone(), storage_prefix, storage_key_name, param)
+        let keyhash = self.storage_key(storage_prefix, storage_key_name, param.into_iter().collect());
+        Self::_get_storage(self.url.clone(), &keyhash, at)
     }
 
-    pub fn get_storage_double_map(
+    /// Reads a plain storage value and decodes it as `V`. Returns `None` if it is absent.
+    /// All storage reads take the hash of the block to read the state at, `None` meaning
+    /// the best block.
+    pub fn get_storage_value<V: Decode>(
         &self,
-        storage_prefix: &str,
+        module: &str,
         storage_key_name: &str,
-        first: Vec<u8>,
-        second: Vec<u8>,
-    ) -> WsResult<String> {
-        Self::_get_storage_double_map(self.url.clone(), storage_prefix, storage_key_name,
-                                      first, second)
+        at: Option<Hash>,
+    ) -> WsResult<Option<V>> {
+        let keyhash = self.storage_key(module, storage_key_name, vec![]);
+        self._get_storage_decoded(module, storage_key_name, &keyhash, at)
+    }
+
+    /// Reads the value stored under `key` in a storage map and decodes it as `V`.
+    pub fn get_storage_map<K: Encode, V: Decode>(
+        &self,
+        module: &str,
+        storage_key_name: &str,
+        key: K,
+        at: Option<Hash>,
+    ) -> WsResult<Option<V>> {
+        let keyhash = self.storage_key(module, storage_key_name, vec![key.encode()]);
+        self._get_storage_decoded(module, storage_key_name, &keyhash, at)
+    }
+
+    /// Reads the value stored under `(first, second)` in a double map and decodes it as `V`.
+    pub fn get_storage_double_map<K1: Encode, K2: Encode, V: Decode>(
+        &self,
+        module: &str,
+        storage_key_name: &str,
+        first: K1,
+        second: K2,
+        at: Option<Hash>,
+    ) -> WsResult<Option<V>> {
+        let keyhash = self.storage_key(module, storage_key_name, vec![first.encode(), second.encode()]);
+        self._get_storage_decoded(module, storage_key_name, &keyhash, at)
+    }
+
+    /// The weight, dispatch class and fee of `xt`, as if it were included in block `at`, or in
+    /// the best block.
+    pub fn get_fee_info<C: Encode>(&self, xt: &UncheckedExtrinsicV4<C>, at: Option<Hash>) -> WsResult<FeeInfo> {
+        let jsonreq = json_req::payment_query_info(&xt.hex_encode(), at);
+        parse_result(&self.get_request(jsonreq.to_string())?)
     }
 
-    pub fn send_extrinsic(&self, xthex_prefixed: String) -> WsResult<Hash> {
+    /// Whether the signer's free balance covers the fee of `xt` plus `amount`, e.g. the value
+    /// transferred by it.
+    pub fn can_pay<C: Encode>(&self, xt: &UncheckedExtrinsicV4<C>, amount: u128) -> WsResult<bool> {
+        let signer = self.signer.as_ref().ok_or_else(|| {
+            WsError::new(ErrorKind::Internal, "Can't check the balance when no signer is set")
+        })?;
+        let mut arr: [u8; 32] = Default::default();
+        arr.clone_from_slice(signer.public().as_ref());
+        // the balance and the fee as of the same block
+        let at = Some(self.get_best_head()?);
+        let free = self.get_free_balance_at(&AccountId32::from(arr), at)?;
+        let fee = self.get_fee_info(xt, at)?.partial_fee;
+        Ok(U256::from(free) >= U256::from(fee) + U256::from(amount))
+    }
+
+    /// The extrinsics waiting in the transaction pool, decoded with their calls resolved from
+    /// the metadata. Extrinsics that can't be decoded are returned raw.
+    pub fn get_pending_extrinsics(&self) -> WsResult<Vec<Result<BlockExtrinsic, RawExtrinsic>>> {
+        let jsonreq = json_req::author_pending_extrinsics();
+        let extrinsics: Vec<String> = parse_result(&self.get_request(jsonreq.to_string())?)?;
+        Ok(chain::decode_extrinsics(&self.metadata, extrinsics))
+    }
+
+    /// Removes extrinsics from the transaction pool, together with the ones depending on them.
+    /// Returns the hashes of all removed extrinsics.
+    pub fn remove_extrinsics(&self, extrinsics: Vec<ExtrinsicOrHash>) -> WsResult<Vec<Hash>> {
+        let jsonreq = json_req::author_remove_extrinsic(&extrinsics);
+        parse_result(&self.get_request(jsonreq.to_string())?)
+    }
+
+    /// Inserts a key of `key_type`, e.g. `"babe"` or `"gran"`, into the node's keystore. The
+    /// node must allow unsafe RPCs.
+    pub fn insert_key(&self, key_type: &str, suri: &str, public: &[u8]) -> WsResult<()> {
+        let jsonreq = json_req::author_insert_key(key_type, suri, public);
+        parse_result(&self.get_request(jsonreq.to_string())?)
+    }
+
+    /// Generates new session keys in the node's keystore and returns their public keys,
+    /// SCALE encoded, ready to be passed to `Session::set_keys`.
+    pub fn rotate_keys(&self) -> WsResult<Vec<u8>> {
+        let jsonreq = json_req::author_rotate_keys();
+        let keys: String = parse_result(&self.get_request(jsonreq.to_string())?)?;
+        hexstr_to_vec(keys).map_err(|e| WsError::new(ErrorKind::Protocol, format!("invalid keys: {:?}", e)))
+    }
+
+    /// Whether the node's keystore holds the private key of `public` for `key_type`.
+    pub fn has_key(&self, public: &[u8], key_type: &str) -> WsResult<bool> {
+        let jsonreq = json_req::author_has_key(public, key_type);
+        parse_result(&self.get_request(jsonreq.to_string())?)
+    }
+
+    /// Submits the extrinsic and waits until it is finalized. Then looks it up in the
+    /// finalized block and collects the events it emitted. Fails only if the extrinsic is not
+    /// finalized, problems collecting the events are reported in the `ExtrinsicReport`.
+    pub fn send_extrinsic(&self, xthex_prefixed: String) -> WsResult<ExtrinsicReport> {
         debug!("sending extrinsic: {:?}", xthex_prefixed);
 
         let jsonreq = json_req::author_submit_and_watch_extrinsic(&xthex_prefixed).to_string();
 
         let (result_in, result_out) = channel();
-        rpc::send_extrinsic_and_wait_until_finalized(
-            self.url.clone(),
-            jsonreq.clone(),
-            result_in.clone(),
-        );
+        rpc::send_extrinsic_and_wait_until_finalized(self.url.clone(), jsonreq, result_in);
+
+        // the final status, see `rpc::client::on_extrinsic_msg`
+        let status = result_out
+            .recv()
+            .map_err(|_| WsError::new(ErrorKind::Protocol, "extrinsic was not finalized"))?;
+        let finalized = serde_json::from_str::<serde_json::Value>(&status)
+            .ok()
+            .and_then(|status| status["finalized"].as_str().map(|hash| hash.to_string()));
+        let block_hash = match finalized {
+            Some(hash) => hexstr_to_hash(hash)
+                .map_err(|e| WsError::new(ErrorKind::Protocol, format!("invalid block hash: {:?}", e)))?,
+            None => {
+                let msg = format!("extrinsic was not finalized: {}", status);
+                return Err(WsError::new(ErrorKind::Protocol, msg));
+            }
+        };
 
-        Ok(hexstr_to_hash(result_out.recv().unwrap()).unwrap())
+        let extrinsic_index = match self.find_extrinsic(block_hash, &xthex_prefixed) {
+            Ok(index) => index,
+            Err(e) => {
+                let events = Err(format!("could not find the extrinsic: {}", e));
+                return Ok(ExtrinsicReport { block_hash, extrinsic_index: None, events, result: None });
+            }
+        };
+        let events = self
+            .get_events(Some(block_hash))
+            .map(|events| {
+                events
+                    .into_iter()
+                    .filter(|e| e.phase == Phase::ApplyExtrinsic(extrinsic_index))
+                    .collect::<Vec<_>>()
+            })
+            .map_err(|e| e.to_string());
+        let result = events.as_ref().ok().and_then(|events| events::dispatch_result(events));
+
+        Ok(ExtrinsicReport { block_hash, extrinsic_index: Some(extrinsic_index), events, result })
+    }
+
+    /// The index of the hex encoded extrinsic in block `at`.
+    fn find_extrinsic(&self, at: Hash, xthex_prefixed: &str) -> WsResult<u32> {
+        let jsonreq = json_req::chain_get_block(Some(at));
+        let block: serde_json::Value = parse_result(&self.get_request(jsonreq.to_string())?)?;
+        let extrinsics: Vec<String> = parse_result(&block["block"]["extrinsics"].to_string())?;
+        extrinsics
+            .iter()
+            .position(|xt| xt.eq_ignore_ascii_case(xthex_prefixed))
+            .map(|index| index as u32)
+            .ok_or_else(|| WsError::new(ErrorKind::Protocol, format!("extrinsic not found in block {:?}", at)))
+    }
+
+    /// Composes an extrinsic like `compose_extrinsic!`, but takes the call arguments as json
+    /// and encodes them according to the argument types in the metadata. Fails on arguments
+    /// that don't fit the metadata and if the signer's nonce can't be fetched.
+    pub fn compose_extrinsic_json(
+        &self,
+        module: &str,
+        call: &str,
+        args: &serde_json::Value,
+    ) -> WsResult<UncheckedExtrinsicV4<RawCall>> {
+        info!("Composing json extrinsic for module {:?} and call {:?}", module, call);
+        let call = dynamic::compose_call_json(&self.metadata, &self.types, module, call, args)
+            .map_err(|e| WsError::new(ErrorKind::Protocol, format!("could not compose call: {}", e)))?;
+
+        Ok(match self.signer.clone() {
+            Some(signer) => compose_extrinsic_offline!(
+                signer,
+                call,
+                self.get_nonce().map_err(|e| WsError::new(ErrorKind::Internal, e.to_string()))?,
+                self.genesis_hash,
+                self.runtime_version.spec_version
+            ),
+            None => UncheckedExtrinsicV4 {
+                signature: None,
+                function: call,
+            },
+        })
     }
 
     pub fn subscribe_events(&self, sender: ThreadOut<String>) {
         debug!("subscribing to events");
-        let key = storage_key_hash("System", "Events", None);
-        let jsonreq = json_req::state_subscribe_storage(&key).to_string();
+        let key = self.storage_key("System", "Events", vec![]);
+        let jsonreq = json_req::state_subscribe_storage(&[key]).to_string();
 
         rpc::start_event_subscriber(self.url.clone(), jsonreq.clone(), sender.clone());
     }
+
+    /// Decodes the hex encoded `System::Events` value delivered by `subscribe_events`.
+    pub fn decode_events(&self, events_hexstr: String) -> Result<Vec<EventRecord>, dynamic::Error> {
+        events::decode_events_hexstr(&self.metadata, &self.types, events_hexstr)
+    }
+
+    /// The events emitted in block `at`, or in the best block.
+    pub fn get_events(&self, at: Option<Hash>) -> WsResult<Vec<EventRecord>> {
+        self.get_events_with(&self.metadata, at)
+    }
+
+    /// Like `get_events`, but decodes them with `metadata`, e.g. the one of an older runtime.
+    fn get_events_with(&self, metadata: &NodeMetadata, at: Option<Hash>) -> WsResult<Vec<EventRecord>> {
+        let key = self.storage_key("System", "Events", vec![]);
+        match hexstr_to_opt_vec(Self::_get_storage(self.url.clone(), &key, at)?) {
+            Ok(Some(bytes)) => events::decode_events(metadata, &self.types, &mut bytes.as_slice())
+                .map_err(|e| WsError::new(ErrorKind::Protocol, format!("could not decode events: {}", e))),
+            Ok(None) => Ok(vec![]),
+            Err(e) => Err(WsError::new(ErrorKind::Protocol, format!("invalid events: {:?}", e))),
+        }
+    }
+
+    /// The hash of the block with the given number, or of the best block if `number` is
+    /// `None`. Returns `None` for blocks the node doesn't know yet.
+    pub fn get_block_hash(&self, number: Option<u32>) -> WsResult<Option<Hash>> {
+        let jsonreq = json_req::chain_get_block_hash(number);
+        parse_result(&self.get_request(jsonreq.to_string())?)
+    }
+
+    /// The hash of the best block. Like `get_block_hash(None)`, but the best block always exists.
+    pub fn get_best_head(&self) -> WsResult<Hash> {
+        self.get_block_hash(None)?
+            .ok_or_else(|| WsError::new(ErrorKind::Protocol, "node returned no best block"))
+    }
+
+    /// The hash of the last finalized block.
+    pub fn get_finalized_head(&self) -> WsResult<Hash> {
+        let jsonreq = json_req::chain_get_finalized_head();
+        parse_result(&self.get_request(jsonreq.to_string())?)
+    }
+
+    /// Walks the blocks `from..=to` and yields the decoded events of each, in block order.
+    /// Blocks the node doesn't know yet yield an error. Events are decoded with the metadata
+    /// of the runtime that executed the block, i.e. the one of its parent. Runtimes other
+    /// than the cached one have their metadata fetched once per `spec_version`.
+    pub fn scan_events<'a>(&'a self, from: u32, to: u32) -> impl Iterator<Item = WsResult<BlockEvents>> + 'a {
+        let mut metadata_cache: HashMap<u32, NodeMetadata> = HashMap::new();
+        (from..=to).map(move |number| {
+            let hash = self.get_block_hash(Some(number))?.ok_or_else(|| {
+                WsError::new(ErrorKind::Protocol, format!("block {} not found", number))
+            })?;
+            // a runtime upgrade takes effect in the block after the one that set the code
+            let executed_by = match number {
+                0 => hash,
+                _ => self.get_block_hash(Some(number - 1))?.unwrap_or(hash),
+            };
+            let spec_version = Self::_fetch_runtime_version(self.url.clone(), Some(executed_by))?.spec_version;
+            if spec_version == self.runtime_version.spec_version {
+                let events = self.get_events(Some(hash))?;
+                return Ok(BlockEvents { number, hash, events });
+            }
+            if !metadata_cache.contains_key(&spec_version) {
+                let meta = Self::_fetch_metadata(self.url.clone(), Some(executed_by))?;
+                metadata_cache.insert(spec_version, node_metadata::parse_metadata(&meta));
+            }
+            let events = self.get_events_with(&metadata_cache[&spec_version], Some(hash))?;
+            Ok(BlockEvents { number, hash, events })
+        })
+    }
+
+    /// Subscribes to events and sends the decoded ones matching `filter`, one by one.
+    pub fn subscribe_events_filtered(&self, filter: EventFilter, sender: ThreadOut<EventRecord>) {
+        let (events_in, events_out) = channel();
+        self.subscribe_events(events_in);
+
+        let metadata = self.metadata.clone();
+        let types = self.types.clone();
+        forward(events_out, sender, move |events_str| {
+            match events::decode_events_hexstr(&metadata, &types, events_str) {
+                Ok(records) => records.into_iter().filter(|r| filter.matches(r)).collect::<Vec<_>>(),
+                Err(e) => {
+                    error!("could not decode events: {}", e);
+                    vec![]
+                }
+            }
+        });
+    }
+
+    /// Subscribes to the event `name` of `module` and sends its arguments decoded as `T`.
+    pub fn subscribe_event_args<T: Decode + Send + 'static>(&self, module: &str, name: &str, sender: ThreadOut<T>) {
+        let (records_in, records_out) = channel();
+        self.subscribe_events_filtered(EventFilter::new().event(module, name), records_in);
+
+        forward(records_out, sender, |record: EventRecord| match record.decode_args::<T>() {
+            Ok(args) => Some(args),
+            Err(e) => {
+                error!("could not decode {}::{} arguments: {:?}", record.module, record.name, e);
+                None
+            }
+        });
+    }
+
+    /// Subscribes to changes of the storage `keys`, as built by `storage_key`. The first
+    /// notification holds the current values.
+    pub fn subscribe_storage(&self, keys: Vec<String>, sender: ThreadOut<StorageChangeSet<Vec<u8>>>) {
+        self._subscribe_storage(keys, sender, Ok)
+    }
+
+    /// Like `subscribe_storage`, but decodes the values as `V`. A value that fails to decode is
+    /// kept as its decode error, like in `query_storage_decoded`.
+    pub fn subscribe_storage_decoded<V: Decode + Send + 'static>(
+        &self,
+        keys: Vec<String>,
+        sender: ThreadOut<StorageChangeSet<Result<V, codec::Error>>>,
+    ) {
+        self._subscribe_storage(keys, sender, |bytes| Ok(V::decode(&mut bytes.as_slice())))
+    }
+
+    fn _subscribe_storage<V, F>(&self, keys: Vec<String>, sender: ThreadOut<StorageChangeSet<V>>, decode: F)
+    where
+        V: Send + 'static,
+        F: Fn(Vec<u8>) -> Result<V, codec::Error> + Send + 'static,
+    {
+        debug!("subscribing to storage keys {:?}", keys);
+        let jsonreq = json_req::state_subscribe_storage(&keys).to_string();
+        let (changes_in, changes_out) = channel();
+        rpc::start_subscriber(self.url.clone(), jsonreq, changes_in, "state_storage");
+
+        forward(changes_out, sender, move |changes_str| match parse_change_set(&changes_str, &decode) {
+            Ok(change_set) => Some(change_set),
+            Err(e) => {
+                error!("could not parse storage change set: {}", e);
+                None
+            }
+        });
+    }
+
+    /// Subscribes to the headers of new best blocks. Reorgs and skipped blocks are passed on
+    /// as the node reports them.
+    pub fn subscribe_new_heads(&self, sender: ThreadOut<Header>) {
+        debug!("subscribing to new heads");
+        let jsonreq = json_req::chain_subscribe_new_heads().to_string();
+        self._subscribe_heads(jsonreq, "chain_newHead", sender)
+    }
+
+    /// Subscribes to the headers of newly finalized blocks. The node may skip blocks when
+    /// several are finalized at once, see `subscribe_all_finalized_heads`.
+    pub fn subscribe_finalized_heads(&self, sender: ThreadOut<Header>) {
+        debug!("subscribing to finalized heads");
+        let jsonreq = json_req::chain_subscribe_finalized_heads().to_string();
+        self._subscribe_heads(jsonreq, "chain_finalizedHead", sender)
+    }
+
+    /// Like `subscribe_finalized_heads`, but sends the header of every finalized block in
+    /// order, fetching the ones the node skipped.
+    pub fn subscribe_all_finalized_heads(&self, sender: ThreadOut<Header>) {
+        let (headers_in, headers_out) = channel();
+        self.subscribe_finalized_heads(headers_in);
+
+        let url = self.url.clone();
+        let mut last: Option<u32> = None;
+        forward(headers_out, sender, move |header: Header| {
+            let headers = match last {
+                Some(last) if header.number <= last => {
+                    debug!("skipping already finalized block {}", header.number);
+                    vec![]
+                }
+                Some(last) => chain::fill_gap(last, header.clone(), |hash| {
+                    Self::_get_header(url.clone(), Some(hash))?.ok_or_else(|| {
+                        WsError::new(ErrorKind::Protocol, format!("block {:?} not found", hash))
+                    })
+                })
+                .unwrap_or_else(|e| {
+                    error!("could not fetch skipped finalized blocks: {}", e);
+                    vec![header]
+                }),
+                None => vec![header],
+            };
+            if let Some(header) = headers.last() {
+                last = Some(header.number);
+            }
+            headers
+        });
+    }
+
+    fn _subscribe_heads(&self, jsonreq: String, method: &'static str, sender: ThreadOut<Header>) {
+        let (headers_in, headers_out) = channel();
+        rpc::start_subscriber(self.url.clone(), jsonreq, headers_in, method);
+
+        forward(headers_out, sender, |header_str: String| match serde_json::from_str::<Header>(&header_str) {
+            Ok(header) => Some(header),
+            Err(e) => {
+                error!("could not parse header: {:?}", e);
+                None
+            }
+        });
+    }
+
+    /// Subscribes to `state_subscribeRuntimeVersion`. Every notification is sent as the
+    /// json string of the `RuntimeVersion`; the first one is the current version.
+    pub fn subscribe_runtime_version(&self, sender: ThreadOut<String>) {
+        debug!("subscribing to runtime version");
+        let jsonreq = json_req::state_subscribe_runtime_version().to_string();
+
+        rpc::start_subscriber(self.url.clone(), jsonreq, sender, "state_runtimeVersion");
+    }
+
+    /// Watches the node for runtime upgrades. Whenever the `spec_version` differs from the
+    /// last known one, the metadata is fetched and parsed again and a `RuntimeUpgrade` is sent.
+    /// Apply it with `update_runtime` before composing further extrinsics. If the metadata
+    /// can't be fetched, the error is sent instead and the cached runtime is stale until the
+    /// caller calls `refresh_runtime`. The next notification retries the fetch.
+    pub fn watch_runtime_upgrades(&self, sender: ThreadOut<WsResult<RuntimeUpgrade>>) {
+        let (version_in, version_out) = channel();
+        self.subscribe_runtime_version(version_in);
+
+        let url = self.url.clone();
+        let mut spec_version = self.runtime_version.spec_version;
+        forward(version_out, sender, move |version_str: String| {
+            let runtime_version: RuntimeVersion = match serde_json::from_str(&version_str) {
+                Ok(v) => v,
+                Err(e) => {
+                    error!("could not parse runtime version: {:?}", e);
+                    return None;
+                }
+            };
+            if runtime_version.spec_version == spec_version {
+                return None;
+            }
+            info!("Runtime upgraded from spec_version {} to {}",
+                  spec_version, runtime_version.spec_version);
+
+            let new_spec_version = runtime_version.spec_version;
+            let upgrade = Self::_fetch_metadata(url.clone(), None).map(|meta| RuntimeUpgrade {
+                runtime_version,
+                metadata: node_metadata::parse_metadata(&meta),
+            });
+            match &upgrade {
+                // only now the upgrade is handled, otherwise retry on the next notification
+                Ok(_) => spec_version = new_spec_version,
+                Err(e) => error!("could not fetch metadata after runtime upgrade: {}", e),
+            }
+            Some(upgrade)
+        });
+    }
+
+    /// Replaces the cached runtime version and metadata with the ones of an upgrade.
+    pub fn update_runtime(&mut self, upgrade: RuntimeUpgrade) {
+        self.runtime_version = upgrade.runtime_version;
+        self.metadata = upgrade.metadata;
+    }
+
+    /// Fetches the runtime version and, if the `spec_version` changed, refreshes the metadata too.
+    /// Returns true if the cached runtime was updated. On errors the cached runtime is kept.
+    pub fn refresh_runtime(&mut self) -> WsResult<bool> {
+        let runtime_version = Self::_fetch_runtime_version(self.url.clone(), None)?;
+        if runtime_version.spec_version == self.runtime_version.spec_version {
+            return Ok(false);
+        }
+        info!("Runtime upgraded from spec_version {} to {}",
+              self.runtime_version.spec_version, runtime_version.spec_version);
+
+        let meta = Self::_fetch_metadata(self.url.clone(), None)?;
+        self.update_runtime(RuntimeUpgrade {
+            runtime_version,
+            metadata: node_metadata::parse_metadata(&meta),
+        });
+        Ok(true)
+    }
+}
+
+/// Whether the node at `url` answers a health request within `IS_ONLINE_TIMEOUT`. Check this
+/// before `Api::new`, which panics if the node is unreachable.
+#[cfg(feature = "std")]
+pub fn is_online(url: &str) -> bool {
+    let (result_in, result_out) = channel();
+    rpc::get(url.to_string(), json_req::system_health().to_string(), result_in);
+
+    // the rpc client thread hangs up without a response if it can't connect
+    result_out
+        .recv_timeout(IS_ONLINE_TIMEOUT)
+        .ok()
+        .and_then(|response| serde_json::from_str::<serde_json::Value>(&response).ok())
+        .map_or(false, |value| serde_json::from_value::<Health>(value["result"].clone()).is_ok())
+}
+
+/// Spawns a thread that maps every message of `receiver` to any number of items with `map`
+/// and sends them to `sender`, until either side hangs up.
+#[cfg(feature = "std")]
+fn forward<T, U, I, F>(receiver: Receiver<T>, sender: ThreadOut<U>, mut map: F)
+where
+    T: Send + 'static,
+    U: Send + 'static,
+    I: IntoIterator<Item = U>,
+    F: FnMut(T) -> I + Send + 'static,
+{
+    thread::spawn(move || {
+        for message in receiver.iter() {
+            for item in map(message) {
+                if sender.send(item).is_err() {
+                    // the receiving side hung up, nobody is interested anymore
+                    return;
+                }
+            }
+        }
+    });
+}
+
+/// Pairs the encoded map keys with the hashers declared for them, defaulting to blake2_256.
+#[cfg(feature = "std")]
+fn with_hashers(hashers: &[StorageHasher], keys: Vec<Vec<u8>>) -> Vec<(StorageHasher, Vec<u8>)> {
+    keys.into_iter()
+        .enumerate()
+        .map(|(i, k)| (hashers.get(i).cloned().unwrap_or(StorageHasher::Blake2_256), k))
+        .collect()
+}
+
+/// Parses a `StorageChangeSet` as sent by the node and decodes its values with `decode`.
+/// Fails as a whole if a value is not valid hex or `decode` fails, so no change is dropped.
+#[cfg(feature = "std")]
+fn parse_change_set<V, F>(changes_str: &str, decode: F) -> WsResult<StorageChangeSet<V>>
+where
+    F: Fn(Vec<u8>) -> Result<V, codec::Error>,
+{
+    #[derive(serde::Deserialize)]
+    struct RpcChangeSet {
+        block: Hash,
+        changes: Vec<(String, Option<String>)>,
+    }
+
+    let change_set: RpcChangeSet = parse_result(changes_str)?;
+    let mut changes = Vec::new();
+    for (key, value) in change_set.changes {
+        let value = match value {
+            Some(hexstr) => {
+                let bytes = hexstr_to_vec(hexstr)
+                    .map_err(|e| WsError::new(ErrorKind::Protocol, format!("invalid value of {}: {:?}", key, e)))?;
+                let value = decode(bytes).map_err(|e| {
+                    WsError::new(ErrorKind::Protocol, format!("could not decode value of {}: {:?}", key, e))
+                })?;
+                Some(value)
+            }
+            None => None,
+        };
+        changes.push((key, value));
+    }
+    Ok(StorageChangeSet { block: change_set.block, changes })
 }
 
+/// Whether the node answered with the JSON-RPC error "method not found", i.e. it doesn't
+/// support the request.
+#[cfg(feature = "std")]
+fn is_method_not_found(e: &WsError) -> bool {
+    const METHOD_NOT_FOUND: i64 = -32601;
+    match e.kind {
+        ErrorKind::Protocol => serde_json::from_str::<serde_json::Value>(&e.details)
+            .map(|err| err["code"].as_i64() == Some(METHOD_NOT_FOUND))
+            .unwrap_or(false),
+        _ => false,
+    }
+}
 
+/// Parses the json `result` of a request, as returned by `Api::get_request`.
 #[cfg(feature = "std")]
-pub fn is_online(ws_addr: &str) -> websocket::WebSocketResult<bool> {
-    let mut client = ClientBuilder::new(&ws_addr).unwrap();
-    let client = client.connect(None)?;
-    let _ = client.shutdown()?;
-    Ok(true)
+fn parse_result<T: serde::de::DeserializeOwned>(result: &str) -> WsResult<T> {
+    serde_json::from_str(result)
+        .map_err(|e| WsError::new(ErrorKind::Protocol, format!("unexpected result {}: {}", result, e)))
 }
@@ -0,0 +1,337 @@
+// Copyright 2019 Liebi Technologies.
+// This file is part of Bifrost.
+
+// Bifrost is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Bifrost is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Bifrost.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Compares two `NodeMetadata` values, e.g. before and after a runtime upgrade, and reports
+//! which modules, calls, events and storage items were added, removed or changed.
+
+use crate::node_metadata::{
+    call_module_index, event_module_index, Call, Event, Module, NodeMetadata, Storage,
+    StorageType,
+};
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub enum ItemKind {
+    Call,
+    Event,
+    Storage,
+}
+
+/// A call, event or storage item reduced to what matters for compatibility:
+/// its name, its index inside the module and its signature.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Item {
+    pub name: String,
+    pub index: usize,
+    pub signature: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub enum ItemDiff {
+    Added(ItemKind, Item),
+    Removed(ItemKind, Item),
+    Changed { kind: ItemKind, old: Item, new: Item },
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ModuleChanges {
+    pub name: String,
+    /// (old, new) index of the module in the call enum, if it changed.
+    pub call_index: Option<(Option<usize>, Option<usize>)>,
+    /// (old, new) index of the module in the event enum, if it changed.
+    pub event_index: Option<(Option<usize>, Option<usize>)>,
+    pub items: Vec<ItemDiff>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub enum ModuleDiff {
+    Added(String),
+    Removed(String),
+    Changed(ModuleChanges),
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct MetadataDiff {
+    pub modules: Vec<ModuleDiff>,
+}
+
+impl MetadataDiff {
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+}
+
+/// Compares `old` against `new`. Modules are matched by name, their items by name too.
+/// An item counts as changed if either its index or its signature differs.
+pub fn diff_metadata(old: &NodeMetadata, new: &NodeMetadata) -> MetadataDiff {
+    let mut modules = Vec::new();
+
+    for old_mod in old {
+        match new.iter().find(|m| m.name == old_mod.name) {
+            Some(new_mod) => {
+                let call_index = (
+                    call_module_index(old, &old_mod.name),
+                    call_module_index(new, &new_mod.name),
+                );
+                let event_index = (
+                    event_module_index(old, &old_mod.name),
+                    event_module_index(new, &new_mod.name),
+                );
+
+                let mut items = Vec::new();
+                diff_items(ItemKind::Call, &call_items(old_mod), &call_items(new_mod), &mut items);
+                diff_items(ItemKind::Event, &event_items(old_mod), &event_items(new_mod), &mut items);
+                diff_items(ItemKind::Storage, &storage_items(old_mod), &storage_items(new_mod), &mut items);
+
+                let changes = ModuleChanges {
+                    name: old_mod.name.clone(),
+                    call_index: Some(call_index).filter(|(o, n)| o != n),
+                    event_index: Some(event_index).filter(|(o, n)| o != n),
+                    items,
+                };
+                if changes.call_index.is_some() || changes.event_index.is_some() || !changes.items.is_empty() {
+                    modules.push(ModuleDiff::Changed(changes));
+                }
+            }
+            None => modules.push(ModuleDiff::Removed(old_mod.name.clone())),
+        }
+    }
+
+    for new_mod in new {
+        if !old.iter().any(|m| m.name == new_mod.name) {
+            modules.push(ModuleDiff::Added(new_mod.name.clone()));
+        }
+    }
+
+    MetadataDiff { modules }
+}
+
+fn diff_items(kind: ItemKind, old: &[Item], new: &[Item], out: &mut Vec<ItemDiff>) {
+    for o in old {
+        match new.iter().find(|n| n.name == o.name) {
+            Some(n) if n != o => out.push(ItemDiff::Changed {
+                kind: kind.clone(),
+                old: o.clone(),
+                new: n.clone(),
+            }),
+            Some(_) => {}
+            None => out.push(ItemDiff::Removed(kind.clone(), o.clone())),
+        }
+    }
+    for n in new {
+        if !old.iter().any(|o| o.name == n.name) {
+            out.push(ItemDiff::Added(kind.clone(), n.clone()));
+        }
+    }
+}
+
+fn call_signature(call: &Call) -> String {
+    let args = call
+        .args
+        .iter()
+        .map(|a| format!("{}: {}", a.name, a.ty))
+        .collect::<Vec<_>>();
+    format!("({})", args.join(", "))
+}
+
+fn event_signature(event: &Event) -> String {
+    format!("({})", event.args.join(", "))
+}
+
+fn storage_signature(storage: &Storage) -> String {
+    match &storage.ty {
+        StorageType::Plain(value) => format!(": {}", value),
+        StorageType::Map { hasher, key, value } => {
+            format!(": Map<{}, {}> ({:?})", key, value, hasher)
+        }
+        StorageType::DoubleMap { hasher, key1, key2, value, key2_hasher } => format!(
+            ": DoubleMap<{}, {}, {}> ({:?}, {:?})",
+            key1, key2, value, hasher, key2_hasher
+        ),
+    }
+}
+
+fn call_items(module: &Module) -> Vec<Item> {
+    module
+        .calls
+        .iter()
+        .enumerate()
+        // placeholder pushed for modules with an empty call list
+        .filter(|(_, c)| !c.name.is_empty())
+        .map(|(index, c)| Item { name: c.name.clone(), index, signature: call_signature(c) })
+        .collect()
+}
+
+fn event_items(module: &Module) -> Vec<Item> {
+    module
+        .events
+        .iter()
+        .enumerate()
+        .map(|(index, e)| Item { name: e.name.clone(), index, signature: event_signature(e) })
+        .collect()
+}
+
+fn storage_items(module: &Module) -> Vec<Item> {
+    module
+        .storage
+        .iter()
+        .enumerate()
+        .map(|(index, s)| Item { name: s.name.clone(), index, signature: storage_signature(s) })
+        .collect()
+}
+
+impl fmt::Display for ItemKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ItemKind::Call => write!(f, "call"),
+            ItemKind::Event => write!(f, "event"),
+            ItemKind::Storage => write!(f, "storage"),
+        }
+    }
+}
+
+impl fmt::Display for ItemDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ItemDiff::Added(kind, i) => {
+                write!(f, "+ {} {}{} [index {}]", kind, i.name, i.signature, i.index)
+            }
+            ItemDiff::Removed(kind, i) => {
+                write!(f, "- {} {}{} [index {}]", kind, i.name, i.signature, i.index)
+            }
+            ItemDiff::Changed { kind, old, new } => {
+                write!(f, "~ {} {}", kind, old.name)?;
+                if old.index != new.index {
+                    write!(f, " index {} -> {}", old.index, new.index)?;
+                }
+                if old.signature != new.signature {
+                    write!(f, " {} -> {}", old.signature, new.signature)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for MetadataDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no differences");
+        }
+        for m in &self.modules {
+            match m {
+                ModuleDiff::Added(name) => writeln!(f, "+ module {}", name)?,
+                ModuleDiff::Removed(name) => writeln!(f, "- module {}", name)?,
+                ModuleDiff::Changed(changes) => {
+                    writeln!(f, "~ module {}", changes.name)?;
+                    if let Some((old, new)) = changes.call_index {
+                        writeln!(f, "  call index {:?} -> {:?}", old, new)?;
+                    }
+                    if let Some((old, new)) = changes.event_index {
+                        writeln!(f, "  event index {:?} -> {:?}", old, new)?;
+                    }
+                    for item in &changes.items {
+                        writeln!(f, "  {}", item)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node_metadata::Arg;
+
+    fn call(name: &str, args: &[(&str, &str)]) -> Call {
+        Call {
+            name: name.to_string(),
+            args: args
+                .iter()
+                .map(|(n, ty)| Arg { name: n.to_string(), ty: ty.to_string() })
+                .collect(),
+        }
+    }
+
+    fn event(name: &str, args: &[&str]) -> Event {
+        Event { name: name.to_string(), args: args.iter().map(|a| a.to_string()).collect() }
+    }
+
+    fn module(name: &str, calls: Vec<Call>, events: Vec<Event>) -> Module {
+        Module { name: name.to_string(), calls, events, ..Default::default() }
+    }
+
+    #[test]
+    fn test_identical_metadata_has_no_diff() {
+        let meta = vec![module("Balances", vec![call("transfer", &[("value", "Balance")])], vec![])];
+        assert!(diff_metadata(&meta, &meta.clone()).is_empty());
+    }
+
+    #[test]
+    fn test_added_and_removed_modules() {
+        let old = vec![module("Sudo", vec![call("sudo", &[])], vec![])];
+        let new = vec![module("BridgeEOS", vec![call("prove_action", &[])], vec![])];
+        let diff = diff_metadata(&old, &new);
+        assert_eq!(
+            diff.modules,
+            vec![ModuleDiff::Removed("Sudo".to_string()), ModuleDiff::Added("BridgeEOS".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_changed_call_arguments_and_indices() {
+        let old = vec![
+            module("Sudo", vec![call("sudo", &[])], vec![]),
+            module("Balances", vec![call("transfer", &[("value", "Compact<Balance>")])], vec![event("Transfer", &["AccountId"])]),
+        ];
+        let new = vec![
+            module("Timestamp", vec![call("set", &[("now", "Compact<Moment>")])], vec![]),
+            module("Sudo", vec![call("sudo", &[])], vec![]),
+            module(
+                "Balances",
+                vec![call("set_balance", &[]), call("transfer", &[("value", "Balance")])],
+                vec![event("Transfer", &["AccountId"])],
+            ),
+        ];
+        let diff = diff_metadata(&old, &new);
+
+        match &diff.modules[1] {
+            ModuleDiff::Changed(changes) => {
+                assert_eq!(changes.name, "Balances");
+                assert_eq!(changes.call_index, Some((Some(1), Some(2))));
+                assert_eq!(changes.event_index, None);
+                assert_eq!(changes.items.len(), 2);
+                assert_eq!(
+                    changes.items[0],
+                    ItemDiff::Changed {
+                        kind: ItemKind::Call,
+                        old: Item { name: "transfer".to_string(), index: 0, signature: "(value: Compact<Balance>)".to_string() },
+                        new: Item { name: "transfer".to_string(), index: 1, signature: "(value: Balance)".to_string() },
+                    }
+                );
+                assert_eq!(
+                    changes.items[1],
+                    ItemDiff::Added(ItemKind::Call, Item { name: "set_balance".to_string(), index: 0, signature: "()".to_string() })
+                );
+            }
+            m => panic!("unexpected diff {:?}", m),
+        }
+        assert_eq!(diff.modules[2], ModuleDiff::Added("Timestamp".to_string()));
+    }
+}
@@ -15,9 +15,16 @@
 // along with Bifrost.  If not, see <http://www.gnu.org/licenses/>.
 
 use codec::alloc::string::FromUtf8Error;
+use codec::{Decode, Encode};
 use log::{debug, info};
-use metadata::{DecodeDifferent, RuntimeMetadata, RuntimeMetadataPrefixed};
+use metadata::{
+    DecodeDifferent, RuntimeMetadata, RuntimeMetadataPrefixed, StorageEntryMetadata,
+    StorageEntryModifier, StorageEntryType,
+};
 use serde::{Deserialize, Serialize};
+use std::{error::Error, fs, path::Path};
+
+use crate::utils::StorageHasher;
 
 pub fn pretty_format(metadata: &RuntimeMetadataPrefixed) -> Result<String, FromUtf8Error> {
     let buf = Vec::new();
@@ -29,6 +36,66 @@ pub fn pretty_format(metadata: &RuntimeMetadataPrefixed) -> Result<String, FromU
 
 pub type NodeMetadata = Vec<Module>;
 
+/// Loads the metadata from a file. The file contains either this crate's `NodeMetadata`
+/// serialized as json, or the hex encoded `RuntimeMetadataPrefixed` as returned by `state_getMetadata`.
+pub fn load_metadata<F: AsRef<Path>>(path: F) -> Result<NodeMetadata, Box<dyn Error>> {
+    let content = fs::read_to_string(path)?;
+    let content = content.trim();
+    if content.starts_with('[') {
+        return Ok(serde_json::from_str(content)?);
+    }
+    let bytes = hex::decode(content.trim_matches('\"').trim_start_matches("0x"))
+        .map_err(|e| format!("metadata file is neither json nor hex: {:?}", e))?;
+    let meta = RuntimeMetadataPrefixed::decode(&mut bytes.as_slice())?;
+    Ok(parse_metadata(&meta))
+}
+
+/// Strips the trait paths from a metadata type string, e.g. `<T as Trait>::Balance` or
+/// `T::Balance` become `Balance`, and `<T::Lookup as StaticLookup>::Source` becomes `Address`.
+/// Whitespace is normalized too, including the escaped newlines of multi-line type strings.
+pub fn normalize_type(ty: &str) -> String {
+    let mut ty = ty.replace("<T::Lookup as StaticLookup>::Source", "Address");
+    while let Some(start) = ty.find("<T as ") {
+        match ty[start..].find(">::") {
+            Some(end) => ty.replace_range(start..start + end + 3, ""),
+            None => break,
+        }
+    }
+    ty.replace("T::", "")
+        .replace("\\n", "")
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .replace(",", ", ")
+        .replace(";", "; ")
+}
+
+/// Looks up a storage item. `module` is matched against both the module name and its storage prefix.
+pub fn find_storage<'a>(metadata: &'a NodeMetadata, module: &str, storage_key_name: &str) -> Option<(&'a Module, &'a Storage)> {
+    metadata
+        .iter()
+        .filter(|m| m.name == module || m.storage_prefix == module)
+        .find_map(|m| m.storage.iter().find(|s| s.name == storage_key_name).map(|s| (m, s)))
+}
+
+/// The modules in the order of the runtime's call enum, i.e. only modules with calls.
+pub fn call_modules(metadata: &NodeMetadata) -> impl Iterator<Item = &Module> {
+    metadata.iter().filter(|m| !m.calls.is_empty())
+}
+
+/// Index of the module in the runtime's call enum, i.e. counting only modules with calls.
+pub fn call_module_index(metadata: &NodeMetadata, module: &str) -> Option<usize> {
+    call_modules(metadata).position(|m| m.name == module)
+}
+
+/// Index of the module in the runtime's event enum, i.e. counting only modules with events.
+pub fn event_module_index(metadata: &NodeMetadata, module: &str) -> Option<usize> {
+    metadata
+        .iter()
+        .filter(|m| !m.events.is_empty())
+        .position(|m| m.name == module)
+}
+
 pub trait Print {
     fn print_events(&self);
     fn print_calls(&self);
@@ -53,6 +120,15 @@ pub struct Module {
     pub name: String,
     pub calls: Vec<Call>,
     pub events: Vec<Event>,
+    /// Prefix of the storage keys, usually but not necessarily the module name. Empty in
+    /// snapshots saved before it was recorded.
+    #[serde(default)]
+    pub storage_prefix: String,
+    #[serde(default)]
+    pub storage: Vec<Storage>,
+    /// Names of the module's errors, in the order of their index.
+    #[serde(default)]
+    pub errors: Vec<String>,
 }
 
 impl Module {
@@ -60,7 +136,10 @@ impl Module {
         Module {
             name: format!("{:?}", name).replace("\"", ""),
             calls: Vec::<Call>::new(),
-            events: Vec::<Event>::new()
+            events: Vec::<Event>::new(),
+            storage_prefix: String::new(),
+            storage: Vec::<Storage>::new(),
+            errors: Vec::<String>::new(),
         }
     }
 
@@ -109,6 +188,71 @@ impl Event {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum StorageType {
+    Plain(String),
+    Map { hasher: StorageHasher, key: String, value: String },
+    DoubleMap { hasher: StorageHasher, key1: String, key2: String, value: String, key2_hasher: StorageHasher },
+}
+
+impl StorageType {
+    /// The hashers of the map keys, in order. Empty for plain values.
+    pub fn hashers(&self) -> Vec<StorageHasher> {
+        match self {
+            StorageType::Plain(_) => vec![],
+            StorageType::Map { hasher, .. } => vec![*hasher],
+            StorageType::DoubleMap { hasher, key2_hasher, .. } => vec![*hasher, *key2_hasher],
+        }
+    }
+}
+
+impl From<&metadata::StorageHasher> for StorageHasher {
+    fn from(hasher: &metadata::StorageHasher) -> Self {
+        match hasher {
+            metadata::StorageHasher::Blake2_128 => StorageHasher::Blake2_128,
+            metadata::StorageHasher::Blake2_256 => StorageHasher::Blake2_256,
+            metadata::StorageHasher::Twox128 => StorageHasher::Twox128,
+            metadata::StorageHasher::Twox256 => StorageHasher::Twox256,
+            metadata::StorageHasher::Twox64Concat => StorageHasher::Twox64Concat,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Storage {
+    pub name: String,
+    pub ty: StorageType,
+    /// The encoded value the runtime returns for an absent key, if the item is declared `Default`.
+    #[serde(default)]
+    pub default: Option<Vec<u8>>,
+}
+
+impl Storage {
+    fn new(entry: &StorageEntryMetadata) -> Storage {
+        let fmt = |s: &DecodeDifferent<&'static str, std::string::String>| format!("{:?}", s).replace("\"", "");
+        let ty = match &entry.ty {
+            StorageEntryType::Plain(value) => StorageType::Plain(fmt(value)),
+            StorageEntryType::Map { hasher, key, value, .. } => StorageType::Map {
+                hasher: hasher.into(),
+                key: fmt(key),
+                value: fmt(value),
+            },
+            StorageEntryType::DoubleMap { hasher, key1, key2, value, key2_hasher, .. } => StorageType::DoubleMap {
+                hasher: hasher.into(),
+                key1: fmt(key1),
+                key2: fmt(key2),
+                value: fmt(value),
+                key2_hasher: key2_hasher.into(),
+            },
+        };
+        let default = match entry.modifier {
+            StorageEntryModifier::Default => Vec::<u8>::decode(&mut entry.default.encode().as_slice()).ok(),
+            StorageEntryModifier::Optional => None,
+        };
+        Storage { name: fmt(&entry.name), ty, default }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Arg {
     pub name: String,
@@ -192,6 +336,32 @@ pub fn parse_metadata(metadata: &RuntimeMetadataPrefixed) -> Vec<Module> {
                             _ => debug!("No calls for this module"),
                         }
 
+                        match &module.storage {
+                            Some(DecodeDifferent::Decoded(storage)) => {
+                                debug!("-------------------- storage ----------------");
+                                _mod.storage_prefix = format!("{:?}", storage.prefix).replace("\"", "");
+                                match &storage.entries {
+                                    DecodeDifferent::Decoded(entries) => {
+                                        for entry in entries {
+                                            _mod.storage.push(Storage::new(entry));
+                                        }
+                                    }
+                                    _ => unreachable!("Decoded metadata contains decoded entries; qed"),
+                                }
+                            }
+                            _ => debug!("No storage for this module"),
+                        }
+
+                        match &module.errors {
+                            DecodeDifferent::Decoded(errors) => {
+                                _mod.errors = errors
+                                    .iter()
+                                    .map(|e| format!("{:?}", e.name).replace("\"", ""))
+                                    .collect();
+                            }
+                            _ => unreachable!("Decoded metadata contains decoded errors; qed"),
+                        }
+
                         mod_vec.push(_mod);
                     }
                     for m in &mod_vec {
@@ -206,3 +376,26 @@ pub fn parse_metadata(metadata: &RuntimeMetadataPrefixed) -> Vec<Module> {
     }
     mod_vec
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_type() {
+        assert_eq!(normalize_type("<T::Lookup as StaticLookup>::Source"), "Address");
+        assert_eq!(normalize_type("Compact<T::Balance>"), "Compact<Balance>");
+        assert_eq!(normalize_type("Vec<(<T as Trait>::AccountId, <T as Trait<I>>::Balance)>"), "Vec<(AccountId, Balance)>");
+        assert_eq!(normalize_type("Vec<\\n  u8>"), "Vec<u8>");
+        assert_eq!(normalize_type("[u8;32]"), "[u8; 32]");
+        assert_eq!(normalize_type("ProducerSchedule"), "ProducerSchedule");
+    }
+
+    #[test]
+    fn test_load_snapshot_without_storage() {
+        let metadata: NodeMetadata =
+            serde_json::from_str(r#"[{"name": "Balances", "calls": [], "events": []}]"#).unwrap();
+        assert!(metadata[0].storage.is_empty());
+        assert_eq!(metadata[0].storage_prefix, "");
+    }
+}
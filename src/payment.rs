@@ -0,0 +1,70 @@
+// Copyright 2019 Liebi Technologies.
+// This file is part of Bifrost.
+
+// Bifrost is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Bifrost is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Bifrost.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Fee information of an extrinsic as returned by `payment_queryInfo`.
+
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DispatchClass {
+    Normal,
+    Operational,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeInfo {
+    pub weight: u32,
+    pub class: DispatchClass,
+    /// The fee without the tip, which is paid on top of it.
+    #[serde(deserialize_with = "deserialize_balance")]
+    pub partial_fee: u128,
+}
+
+/// Balances arrive as json numbers, or as hex strings when they exceed the json number range.
+fn deserialize_balance<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrHex {
+        Number(u64),
+        Hex(String),
+    }
+
+    match NumberOrHex::deserialize(deserializer)? {
+        NumberOrHex::Number(n) => Ok(u128::from(n)),
+        NumberOrHex::Hex(h) => u128::from_str_radix(h.trim_start_matches("0x"), 16).map_err(de::Error::custom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_fee_info() {
+        let info: FeeInfo =
+            serde_json::from_str(r#"{"weight": 10000, "class": "normal", "partialFee": 1250000000}"#).unwrap();
+        assert_eq!(info, FeeInfo { weight: 10000, class: DispatchClass::Normal, partial_fee: 1_250_000_000 });
+
+        let info: FeeInfo = serde_json::from_str(
+            r#"{"weight": 0, "class": "operational", "partialFee": "0x100000000000000000"}"#,
+        )
+        .unwrap();
+        assert_eq!(info.class, DispatchClass::Operational);
+        assert_eq!(info.partial_fee, 1u128 << 68);
+    }
+}
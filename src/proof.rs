@@ -0,0 +1,163 @@
+// Copyright 2019 Liebi Technologies.
+// This file is part of Bifrost.
+
+// Bifrost is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Bifrost is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Bifrost.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Storage read proofs as returned by `state_getReadProof`, and their verification against
+//! the state root of a block header. Verification needs no connection to a node, so a proof
+//! can be handed to another party together with the header it was taken at.
+
+use crate::utils::{hexstr_to_vec, storage_key, StorageHasher, StorageLayout};
+use hash_db::{HashDB, EMPTY_PREFIX};
+use primitives::{Blake2Hasher, H256 as Hash};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use trie::{read_trie_value, Layout, MemoryDB};
+
+/// The trie nodes proving the values of a set of keys at block `at`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ReadProof {
+    pub at: Hash,
+    /// The encoded trie nodes, in hex as returned by the node.
+    pub proof: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// A proof node is not valid hex.
+    InvalidNode(String),
+    /// The proof lacks nodes needed to read a key, or doesn't match the state root.
+    Incomplete(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidNode(node) => write!(f, "invalid proof node: {}", node),
+            Error::Incomplete(e) => write!(f, "proof does not match the state root: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ReadProof {
+    /// Decodes the hex encoded trie nodes.
+    pub fn nodes(&self) -> Result<Vec<Vec<u8>>, Error> {
+        self.proof
+            .iter()
+            .map(|node| hexstr_to_vec(node.clone()).map_err(|_| Error::InvalidNode(node.clone())))
+            .collect()
+    }
+
+    /// Checks the proof against `state_root` and returns the proven value of every key in
+    /// `keys`, `None` meaning the key is proven to be absent.
+    pub fn verify(&self, state_root: &Hash, keys: &[Vec<u8>]) -> Result<Vec<Option<Vec<u8>>>, Error> {
+        verify_proof(state_root, &self.nodes()?, keys)
+    }
+}
+
+/// Checks the trie nodes in `proof` against `state_root` and reads the values of `keys` from
+/// them. Fails if a key can't be read because the proof misses nodes or was taken at a
+/// different state.
+pub fn verify_proof(state_root: &Hash, proof: &[Vec<u8>], keys: &[Vec<u8>]) -> Result<Vec<Option<Vec<u8>>>, Error> {
+    let mut db = MemoryDB::<Blake2Hasher>::default();
+    for node in proof {
+        db.insert(EMPTY_PREFIX, node);
+    }
+    keys.iter()
+        .map(|key| {
+            read_trie_value::<Layout<Blake2Hasher>, _>(&db, state_root, key)
+                .map_err(|e| Error::Incomplete(format!("{:?}", e)))
+        })
+        .collect()
+}
+
+/// Like `verify_proof` for a single storage item, building its key like `utils::storage_key`.
+pub fn verify_storage_value(
+    state_root: &Hash,
+    proof: &[Vec<u8>],
+    layout: StorageLayout,
+    module: &str,
+    storage_key_name: &str,
+    keys: &[(StorageHasher, Vec<u8>)],
+) -> Result<Option<Vec<u8>>, Error> {
+    let key = storage_key(layout, module, storage_key_name, keys);
+    Ok(verify_proof(state_root, proof, &[key])?.remove(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::Encode;
+    use trie::{TrieDBMut, TrieMut};
+
+    /// Builds a trie from `entries` and returns its root together with all of its nodes.
+    fn trie(entries: &[(Vec<u8>, Vec<u8>)]) -> (Hash, Vec<Vec<u8>>) {
+        let mut db = MemoryDB::<Blake2Hasher>::default();
+        let mut root = Hash::default();
+        {
+            let mut trie = TrieDBMut::<Layout<Blake2Hasher>>::new(&mut db, &mut root);
+            for (key, value) in entries {
+                trie.insert(key, value).unwrap();
+            }
+        }
+        let nodes = db.drain().into_iter().map(|(_, (node, _))| node.to_vec()).collect();
+        (root, nodes)
+    }
+
+    #[test]
+    fn test_verify_proof() {
+        let alice = [1u8; 32].to_vec();
+        let nonce_key = storage_key(
+            StorageLayout::Prefixed,
+            "System",
+            "AccountNonce",
+            &[(StorageHasher::Blake2_128Concat, alice.clone())],
+        );
+        let other_key = b"other".to_vec();
+        let (root, nodes) = trie(&[(nonce_key.clone(), 5u32.encode()), (other_key.clone(), vec![1])]);
+
+        assert_eq!(
+            verify_proof(&root, &nodes, &[nonce_key, other_key, b"absent".to_vec()]),
+            Ok(vec![Some(5u32.encode()), Some(vec![1]), None])
+        );
+        assert_eq!(
+            verify_storage_value(
+                &root,
+                &nodes,
+                StorageLayout::Prefixed,
+                "System",
+                "AccountNonce",
+                &[(StorageHasher::Blake2_128Concat, alice)],
+            ),
+            Ok(Some(5u32.encode()))
+        );
+    }
+
+    #[test]
+    fn test_verify_proof_rejects_wrong_root_and_missing_nodes() {
+        let (root, nodes) = trie(&[(b"key".to_vec(), vec![1])]);
+        assert!(verify_proof(&Hash::repeat_byte(1), &nodes, &[b"key".to_vec()]).is_err());
+        assert!(verify_proof(&root, &[], &[b"key".to_vec()]).is_err());
+
+        let proof = ReadProof {
+            at: Hash::default(),
+            proof: nodes.iter().map(|n| format!("0x{}", hex::encode(n))).collect(),
+        };
+        assert_eq!(proof.verify(&root, &[b"key".to_vec()]), Ok(vec![Some(vec![1])]));
+        let broken = ReadProof { at: Hash::default(), proof: vec!["0xzz".to_string()] };
+        assert_eq!(broken.verify(&root, &[]), Err(Error::InvalidNode("0xzz".to_string())));
+    }
+}
@@ -17,9 +17,10 @@
 use crate::rpc::json_req::REQUEST_TRANSFER;
 use log::{debug, error, info};
 use std::sync::mpsc::Sender as ThreadOut;
-use ws::{CloseCode, Handler, Handshake, Message, Result, Sender};
+use std::sync::Arc;
+use ws::{CloseCode, Error, ErrorKind, Handler, Handshake, Message, Result, Sender};
 
-pub type OnMessageFn = fn(msg: Message, out: Sender, result: ThreadOut<String>) -> Result<()>;
+pub type OnMessageFn = Arc<dyn Fn(Message, Sender, ThreadOut<String>) -> Result<()> + Send + Sync>;
 
 pub struct RpcClient {
     pub out: Sender,
@@ -42,18 +43,18 @@ impl Handler for RpcClient {
     }
 }
 
+/// Forwards the whole response, so that the caller can tell a `null` result from an error.
 pub fn on_get_request_msg(msg: Message, out: Sender, result: ThreadOut<String>) -> Result<()> {
     let retstr = msg.as_text().unwrap();
-    let value: serde_json::Value = serde_json::from_str(retstr).unwrap();
 
-    result.send(value["result"].to_string()).unwrap();
+    result.send(retstr.to_string()).unwrap();
     out.close(CloseCode::Normal).unwrap();
     Ok(())
 }
 
 pub fn on_subscription_msg(msg: Message, _out: Sender, result: ThreadOut<String>) -> Result<()> {
-    let retstr = msg.as_text().unwrap();
-    let value: serde_json::Value = serde_json::from_str(retstr).unwrap();
+    let value: serde_json::Value = serde_json::from_str(msg.as_text()?)
+        .map_err(|e| Error::new(ErrorKind::Protocol, format!("invalid json message: {}", e)))?;
     match value["id"].as_str() {
         Some(_idstr) => {}
         _ => {
@@ -62,9 +63,12 @@ pub fn on_subscription_msg(msg: Message, _out: Sender, result: ThreadOut<String>
             debug!("method: {:?}", value["method"].as_str());
             match value["method"].as_str() {
                 Some("state_storage") => {
-                    let _changes = &value["params"]["result"]["changes"];
-                    let _res_str = _changes[0][1].as_str().unwrap().to_string();
-                    result.send(_res_str).unwrap();
+                    let changes = &value["params"]["result"]["changes"];
+                    match changes[0][1].as_str() {
+                        Some(res_str) => result.send(res_str.to_string()).unwrap(),
+                        // `null`: the value was removed, e.g. no events in this block
+                        None => debug!("storage value of {} removed", changes[0][0]),
+                    }
                 }
                 _ => error!("unsupported method"),
             }
@@ -73,14 +77,48 @@ pub fn on_subscription_msg(msg: Message, _out: Sender, result: ThreadOut<String>
     Ok(())
 }
 
+/// Forwards the result of every notification of the subscription `method` as json.
+pub fn on_notification_msg(msg: Message, result: ThreadOut<String>, method: &str) -> Result<()> {
+    let value: serde_json::Value = serde_json::from_str(msg.as_text()?)
+        .map_err(|e| Error::new(ErrorKind::Protocol, format!("invalid json message: {}", e)))?;
+    match value["id"].as_str() {
+        Some(_idstr) => {}
+        _ => {
+            // subscriptions
+            debug!("no id field found in response. must be subscription");
+            debug!("method: {:?}", value["method"].as_str());
+            if value["method"].as_str() == Some(method) {
+                result
+                    .send(value["params"]["result"].to_string())
+                    .map_err(|e| Error::new(ErrorKind::Internal, e.to_string()))?;
+            } else {
+                error!("unsupported method");
+            }
+        }
+    };
+    Ok(())
+}
+
+/// Follows the status updates of a submitted extrinsic. The final status, or the error of the
+/// submission, is sent as json and closes the connection: `{"finalized": hash}` on success,
+/// otherwise `"invalid"`, `"dropped"`, `{"usurped": hash}` or a JSON-RPC error object.
 pub fn on_extrinsic_msg(msg: Message, out: Sender, result: ThreadOut<String>) -> Result<()> {
-    let retstr = msg.as_text().unwrap();
-    let value: serde_json::Value = serde_json::from_str(retstr).unwrap();
+    let value: serde_json::Value = serde_json::from_str(msg.as_text()?)
+        .map_err(|e| Error::new(ErrorKind::Protocol, format!("invalid json message: {}", e)))?;
+    let finish = |status: &serde_json::Value| {
+        // the caller may have given up already
+        let _ = result.send(status.to_string());
+        out.close(CloseCode::Normal)
+    };
     match value["id"].as_str() {
         Some(idstr) => match idstr.parse::<u32>() {
             Ok(req_id) => match req_id {
                 REQUEST_TRANSFER => match value.get("error") {
-                    Some(err) => error!("ERROR: {:?}", err),
+                    Some(err) => {
+                        error!("ERROR: {:?}", err);
+                        // no updates will follow
+                        finish(err)?;
+                    }
                     _ => debug!("no error"),
                 },
                 _ => debug!("Unknown request id"),
@@ -93,25 +131,26 @@ pub fn on_extrinsic_msg(msg: Message, out: Sender, result: ThreadOut<String>) ->
             debug!("method: {:?}", value["method"].as_str());
             match value["method"].as_str() {
                 Some("author_extrinsicUpdate") => {
-                    match value["params"]["result"].as_str() {
-                        Some(res) => debug!("author_extrinsicUpdate: {}", res),
-                        _ => {
-                            debug!(
-                                "author_extrinsicUpdate: finalized: {}",
-                                value["params"]["result"]["finalized"].as_str().unwrap()
-                            );
-                            // return result to calling thread
-                            result
-                                .send(
-                                    value["params"]["result"]["finalized"]
-                                        .as_str()
-                                        .unwrap()
-                                        .to_string(),
-                                )
-                                .unwrap();
-                            // we've reached the end of the flow. return
-                            out.close(CloseCode::Normal).unwrap();
+                    let status = &value["params"]["result"];
+                    // unit variants are plain strings, the others objects like `{"finalized": hash}`
+                    let variant = match status {
+                        serde_json::Value::String(variant) => Some(variant.as_str()),
+                        serde_json::Value::Object(map) => map.keys().next().map(String::as_str),
+                        _ => None,
+                    };
+                    match variant {
+                        Some("future") | Some("ready") | Some("broadcast") => {
+                            debug!("author_extrinsicUpdate: {}", status)
+                        }
+                        Some("finalized") => {
+                            debug!("author_extrinsicUpdate: {}", status);
+                            finish(status)?;
+                        }
+                        Some("invalid") | Some("dropped") | Some("usurped") => {
+                            error!("author_extrinsicUpdate: {}", status);
+                            finish(status)?;
                         }
+                        _ => error!("unsupported extrinsic status: {}", status),
                     }
                 }
                 _ => error!("unsupported method"),
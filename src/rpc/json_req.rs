@@ -14,68 +14,386 @@
 // You should have received a copy of the GNU General Public License
 // along with Bifrost.  If not, see <http://www.gnu.org/licenses/>.
 
+use primitives::H256 as Hash;
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
 pub const REQUEST_TRANSFER: u32 = 3;
 
-pub fn chain_get_block_hash() -> Value {
-    chain_get_block_hash_with_id(1)
+/// Identifies an extrinsic to remove from the transaction pool, see `author_remove_extrinsic`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExtrinsicOrHash {
+    Hash(Hash),
+    /// The hex encoded extrinsic.
+    Extrinsic(String),
 }
 
-pub fn chain_get_block_hash_with_id(id: u32) -> Value {
+/// The hash of the block with the given number, or of the best block if `number` is `None`.
+pub fn chain_get_block_hash(number: Option<u32>) -> Value {
+    chain_get_block_hash_with_id(number, 1)
+}
+
+pub fn chain_get_block_hash_with_id(number: Option<u32>, id: u32) -> Value {
     json!({
     "method": "chain_getBlockHash",
-    "params": [0],
+    "params": [number],
     "jsonrpc": "2.0",
     "id": id.to_string(),
     })
 }
 
-pub fn state_get_metadata() -> Value {
-    state_get_metadata_with_id(1)
+/// The hash of the last finalized block.
+pub fn chain_get_finalized_head() -> Value {
+    chain_get_finalized_head_with_id(1)
 }
 
-pub fn state_get_metadata_with_id(id: u32) -> Value {
+pub fn chain_get_finalized_head_with_id(id: u32) -> Value {
     json!({
-        "method": "state_getMetadata",
+        "method": "chain_getFinalizedHead",
         "params": null,
         "jsonrpc": "2.0",
         "id": id.to_string(),
     })
 }
 
-pub fn state_get_runtime_version() -> Value {
-    state_get_runtime_version_with_id(1)
+/// The header of block `at`, or of the best block if `at` is `None`.
+pub fn chain_get_header(at: Option<Hash>) -> Value {
+    chain_get_header_with_id(at, 1)
+}
+
+pub fn chain_get_header_with_id(at: Option<Hash>, id: u32) -> Value {
+    json!({
+        "method": "chain_getHeader",
+        "params": [at],
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+/// The block `at`, or the best block if `at` is `None`.
+pub fn chain_get_block(at: Option<Hash>) -> Value {
+    chain_get_block_with_id(at, 1)
+}
+
+pub fn chain_get_block_with_id(at: Option<Hash>, id: u32) -> Value {
+    json!({
+        "method": "chain_getBlock",
+        "params": [at],
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+pub fn state_get_metadata(at: Option<Hash>) -> Value {
+    state_get_metadata_with_id(at, 1)
 }
 
-pub fn state_get_runtime_version_with_id(id: u32) -> Value {
+pub fn state_get_metadata_with_id(at: Option<Hash>, id: u32) -> Value {
+    json!({
+        "method": "state_getMetadata",
+        "params": [at],
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+pub fn state_get_runtime_version(at: Option<Hash>) -> Value {
+    state_get_runtime_version_with_id(at, 1)
+}
+
+pub fn state_get_runtime_version_with_id(at: Option<Hash>, id: u32) -> Value {
     json!({
         "method": "state_getRuntimeVersion",
-        "params": null,
+        "params": [at],
         "jsonrpc": "2.0",
         "id": id.to_string(),
     })
 }
 
-pub fn state_subscribe_storage(key: &str) -> Value {
-    state_subscribe_storage_with_id(key, 1)
+pub fn state_subscribe_storage(keys: &[String]) -> Value {
+    state_subscribe_storage_with_id(keys, 1)
 }
 
-pub fn state_subscribe_storage_with_id(key: &str, id: u32) -> Value {
+pub fn state_subscribe_storage_with_id(keys: &[String], id: u32) -> Value {
     json!({
         "method": "state_subscribeStorage",
-        "params": [[key]],
+        "params": [keys],
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+pub fn chain_subscribe_new_heads() -> Value {
+    chain_subscribe_new_heads_with_id(1)
+}
+
+pub fn chain_subscribe_new_heads_with_id(id: u32) -> Value {
+    json!({
+        "method": "chain_subscribeNewHeads",
+        "params": null,
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+pub fn chain_subscribe_finalized_heads() -> Value {
+    chain_subscribe_finalized_heads_with_id(1)
+}
+
+pub fn chain_subscribe_finalized_heads_with_id(id: u32) -> Value {
+    json!({
+        "method": "chain_subscribeFinalizedHeads",
+        "params": null,
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+pub fn state_subscribe_runtime_version() -> Value {
+    state_subscribe_runtime_version_with_id(1)
+}
+
+pub fn state_subscribe_runtime_version_with_id(id: u32) -> Value {
+    json!({
+        "method": "state_subscribeRuntimeVersion",
+        "params": null,
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+/// Reads the storage at block `at`, or at the best block if `at` is `None`.
+pub fn state_get_storage(key_hash: &str, at: Option<Hash>) -> Value {
+    state_get_storage_with_id(key_hash, at, 1)
+}
+
+pub fn state_get_storage_with_id(key_hash: &str, at: Option<Hash>, id: u32) -> Value {
+    json!({
+        "method": "state_getStorage",
+        "params": [key_hash, at],
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+/// Up to `count` keys starting with `prefix`, following `start_key` if given.
+pub fn state_get_keys_paged(prefix: &str, count: u32, start_key: Option<&str>, at: Option<Hash>) -> Value {
+    state_get_keys_paged_with_id(prefix, count, start_key, at, 1)
+}
+
+pub fn state_get_keys_paged_with_id(
+    prefix: &str,
+    count: u32,
+    start_key: Option<&str>,
+    at: Option<Hash>,
+    id: u32,
+) -> Value {
+    json!({
+        "method": "state_getKeysPaged",
+        "params": [prefix, count, start_key, at],
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+pub fn state_get_keys(prefix: &str, at: Option<Hash>) -> Value {
+    state_get_keys_with_id(prefix, at, 1)
+}
+
+pub fn state_get_keys_with_id(prefix: &str, at: Option<Hash>, id: u32) -> Value {
+    json!({
+        "method": "state_getKeys",
+        "params": [prefix, at],
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+pub fn state_get_pairs(prefix: &str, at: Option<Hash>) -> Value {
+    state_get_pairs_with_id(prefix, at, 1)
+}
+
+pub fn state_get_pairs_with_id(prefix: &str, at: Option<Hash>, id: u32) -> Value {
+    json!({
+        "method": "state_getPairs",
+        "params": [prefix, at],
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+/// Reads `key` from the child trie stored under `child_storage_key`.
+pub fn state_get_child_storage(child_storage_key: &str, key: &str, at: Option<Hash>) -> Value {
+    state_get_child_storage_with_id(child_storage_key, key, at, 1)
+}
+
+pub fn state_get_child_storage_with_id(child_storage_key: &str, key: &str, at: Option<Hash>, id: u32) -> Value {
+    json!({
+        "method": "state_getChildStorage",
+        "params": [child_storage_key, key, at],
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+pub fn state_get_child_storage_hash(child_storage_key: &str, key: &str, at: Option<Hash>) -> Value {
+    state_get_child_storage_hash_with_id(child_storage_key, key, at, 1)
+}
+
+pub fn state_get_child_storage_hash_with_id(child_storage_key: &str, key: &str, at: Option<Hash>, id: u32) -> Value {
+    json!({
+        "method": "state_getChildStorageHash",
+        "params": [child_storage_key, key, at],
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+pub fn state_get_child_storage_size(child_storage_key: &str, key: &str, at: Option<Hash>) -> Value {
+    state_get_child_storage_size_with_id(child_storage_key, key, at, 1)
+}
+
+pub fn state_get_child_storage_size_with_id(child_storage_key: &str, key: &str, at: Option<Hash>, id: u32) -> Value {
+    json!({
+        "method": "state_getChildStorageSize",
+        "params": [child_storage_key, key, at],
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+pub fn state_get_child_keys(child_storage_key: &str, prefix: &str, at: Option<Hash>) -> Value {
+    state_get_child_keys_with_id(child_storage_key, prefix, at, 1)
+}
+
+pub fn state_get_child_keys_with_id(child_storage_key: &str, prefix: &str, at: Option<Hash>, id: u32) -> Value {
+    json!({
+        "method": "state_getChildKeys",
+        "params": [child_storage_key, prefix, at],
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+/// The changes of `keys` in the blocks from `from` up to `to`, or up to the best block.
+pub fn state_query_storage(keys: &[String], from: Hash, to: Option<Hash>) -> Value {
+    state_query_storage_with_id(keys, from, to, 1)
+}
+
+pub fn state_query_storage_with_id(keys: &[String], from: Hash, to: Option<Hash>, id: u32) -> Value {
+    json!({
+        "method": "state_queryStorage",
+        "params": [keys, from, to],
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+pub fn state_get_read_proof(keys: &[String], at: Option<Hash>) -> Value {
+    state_get_read_proof_with_id(keys, at, 1)
+}
+
+pub fn state_get_read_proof_with_id(keys: &[String], at: Option<Hash>, id: u32) -> Value {
+    json!({
+        "method": "state_getReadProof",
+        "params": [keys, at],
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+pub fn system_health() -> Value {
+    system_health_with_id(1)
+}
+
+pub fn system_health_with_id(id: u32) -> Value {
+    json!({
+        "method": "system_health",
+        "params": null,
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+pub fn system_peers() -> Value {
+    system_peers_with_id(1)
+}
+
+pub fn system_peers_with_id(id: u32) -> Value {
+    json!({
+        "method": "system_peers",
+        "params": null,
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+pub fn system_name() -> Value {
+    system_name_with_id(1)
+}
+
+pub fn system_name_with_id(id: u32) -> Value {
+    json!({
+        "method": "system_name",
+        "params": null,
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+pub fn system_version() -> Value {
+    system_version_with_id(1)
+}
+
+pub fn system_version_with_id(id: u32) -> Value {
+    json!({
+        "method": "system_version",
+        "params": null,
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+pub fn system_chain() -> Value {
+    system_chain_with_id(1)
+}
+
+pub fn system_chain_with_id(id: u32) -> Value {
+    json!({
+        "method": "system_chain",
+        "params": null,
         "jsonrpc": "2.0",
         "id": id.to_string(),
     })
 }
 
-pub fn state_get_storage(key_hash: &str) -> Value {
-    state_get_storage_with_id(key_hash, 1)
+pub fn system_properties() -> Value {
+    system_properties_with_id(1)
 }
 
-pub fn state_get_storage_with_id(key_hash: &str, id: u32) -> Value {
-    json_req("state_getStorage", key_hash, id)
+pub fn system_properties_with_id(id: u32) -> Value {
+    json!({
+        "method": "system_properties",
+        "params": null,
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+/// The fee information of an extrinsic, as if it were included in block `at`, or in the
+/// best block if `at` is `None`.
+pub fn payment_query_info(xthex_prefixed: &str, at: Option<Hash>) -> Value {
+    payment_query_info_with_id(xthex_prefixed, at, 1)
+}
+
+pub fn payment_query_info_with_id(xthex_prefixed: &str, at: Option<Hash>, id: u32) -> Value {
+    json!({
+        "method": "payment_queryInfo",
+        "params": [xthex_prefixed, at],
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
 }
 
 pub fn author_submit_and_watch_extrinsic(xthex_prefixed: &str) -> Value {
@@ -90,6 +408,71 @@ pub fn author_submit_and_watch_extrinsic_with_id(xthex_prefixed: &str, id: u32)
     )
 }
 
+pub fn author_pending_extrinsics() -> Value {
+    author_pending_extrinsics_with_id(1)
+}
+
+pub fn author_pending_extrinsics_with_id(id: u32) -> Value {
+    json!({
+        "method": "author_pendingExtrinsics",
+        "params": null,
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+pub fn author_remove_extrinsic(extrinsics: &[ExtrinsicOrHash]) -> Value {
+    author_remove_extrinsic_with_id(extrinsics, 1)
+}
+
+pub fn author_remove_extrinsic_with_id(extrinsics: &[ExtrinsicOrHash], id: u32) -> Value {
+    json!({
+        "method": "author_removeExtrinsic",
+        "params": [extrinsics],
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+pub fn author_insert_key(key_type: &str, suri: &str, public: &[u8]) -> Value {
+    author_insert_key_with_id(key_type, suri, public, 1)
+}
+
+pub fn author_insert_key_with_id(key_type: &str, suri: &str, public: &[u8], id: u32) -> Value {
+    json!({
+        "method": "author_insertKey",
+        "params": [key_type, suri, format!("0x{}", hex::encode(public))],
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+pub fn author_rotate_keys() -> Value {
+    author_rotate_keys_with_id(1)
+}
+
+pub fn author_rotate_keys_with_id(id: u32) -> Value {
+    json!({
+        "method": "author_rotateKeys",
+        "params": null,
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
+pub fn author_has_key(public: &[u8], key_type: &str) -> Value {
+    author_has_key_with_id(public, key_type, 1)
+}
+
+pub fn author_has_key_with_id(public: &[u8], key_type: &str, id: u32) -> Value {
+    json!({
+        "method": "author_hasKey",
+        "params": [format!("0x{}", hex::encode(public)), key_type],
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+    })
+}
+
 fn json_req(method: &str, params: &str, id: u32) -> Value {
     json!({
         "method": method,
@@ -15,9 +15,11 @@
 // along with Bifrost.  If not, see <http://www.gnu.org/licenses/>.
 
 use client::*;
+use log::error;
 use std::sync::mpsc::Sender as ThreadOut;
+use std::sync::Arc;
 use std::thread;
-use ws::connect;
+use ws::{connect, Message, Sender};
 
 mod client;
 pub mod json_req;
@@ -38,22 +40,32 @@ pub fn start_event_subscriber(url: String, json_req: String, result_in: ThreadOu
     start_rpc_client_thread(url, json_req, result_in, on_subscription_msg)
 }
 
-fn start_rpc_client_thread(
-    url: String,
-    jsonreq: String,
-    result_in: ThreadOut<String>,
-    on_message_fn: OnMessageFn,
-) {
+/// Subscribes with `json_req` and forwards the result of every notification of `method`,
+/// e.g. `chain_newHead`, as json.
+pub fn start_subscriber(url: String, json_req: String, result_in: ThreadOut<String>, method: &'static str) {
+    start_rpc_client_thread(url, json_req, result_in, move |msg, _out, result| {
+        on_notification_msg(msg, result, method)
+    })
+}
+
+fn start_rpc_client_thread<F>(url: String, jsonreq: String, result_in: ThreadOut<String>, on_message_fn: F)
+where
+    F: Fn(Message, Sender, ThreadOut<String>) -> ws::Result<()> + Send + Sync + 'static,
+{
+    let on_message_fn: OnMessageFn = Arc::new(on_message_fn);
     let _client = thread::Builder::new()
         .name("client".to_owned())
         .spawn(move || {
-            connect(url, |out| RpcClient {
+            let result = connect(url.clone(), |out| RpcClient {
                 out,
                 request: jsonreq.clone(),
                 result: result_in.clone(),
-                on_message_fn,
-            })
-            .unwrap()
+                on_message_fn: on_message_fn.clone(),
+            });
+            // dropping `result_in` tells the waiting caller that no response will come
+            if let Err(e) = result {
+                error!("could not connect to {}: {}", url, e);
+            }
         })
         .unwrap();
 }
@@ -0,0 +1,82 @@
+// Copyright 2019 Liebi Technologies.
+// This file is part of Bifrost.
+
+// Bifrost is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Bifrost is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Bifrost.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Node information as returned by the `system_*` RPCs.
+
+use primitives::H256 as Hash;
+use serde::{Deserialize, Serialize};
+
+/// The result of `system_health`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Health {
+    pub peers: u32,
+    pub is_syncing: bool,
+    /// Whether the node is expected to have peers, false for a dev chain.
+    pub should_have_peers: bool,
+}
+
+/// A connected peer, as listed by `system_peers`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerInfo {
+    pub peer_id: String,
+    pub roles: String,
+    pub protocol_version: u32,
+    pub best_hash: Hash,
+    pub best_number: u32,
+}
+
+/// The chain properties of `system_properties`. Chain specs may leave any of them out.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Properties {
+    pub ss58_format: Option<u8>,
+    pub token_decimals: Option<u32>,
+    pub token_symbol: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_node_info() {
+        let health: Health =
+            serde_json::from_str(r#"{"peers": 3, "isSyncing": false, "shouldHavePeers": true}"#).unwrap();
+        assert_eq!(health, Health { peers: 3, is_syncing: false, should_have_peers: true });
+
+        let peers: Vec<PeerInfo> = serde_json::from_str(
+            r#"[{
+                "peerId": "QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV",
+                "roles": "FULL",
+                "protocolVersion": 5,
+                "bestHash": "0x0101010101010101010101010101010101010101010101010101010101010101",
+                "bestNumber": 42
+            }]"#,
+        )
+        .unwrap();
+        assert_eq!(peers[0].best_number, 42);
+        assert_eq!(peers[0].best_hash, Hash::repeat_byte(1));
+
+        let properties: Properties =
+            serde_json::from_str(r#"{"ss58Format": 6, "tokenDecimals": 12, "tokenSymbol": "BNC"}"#).unwrap();
+        assert_eq!(properties.ss58_format, Some(6));
+        assert_eq!(properties.token_decimals, Some(12));
+        assert_eq!(properties.token_symbol, Some("BNC".to_string()));
+        assert_eq!(serde_json::from_str::<Properties>("{}").unwrap(), Properties::default());
+    }
+}
@@ -0,0 +1,119 @@
+// Generated by substrate_rpc_client::codegen. Do not edit.
+
+#[allow(dead_code, non_camel_case_types)]
+pub mod sudo {
+    pub mod calls {
+        #[allow(unused_imports)]
+        use super::super::*;
+        #[allow(unused_imports)]
+        use substrate_rpc_client::extrinsic::codec::{Compact, Decode, Encode, Error, Input};
+        #[allow(unused_imports)]
+        use substrate_rpc_client::extrinsic::node_primitives::{AccountId, AccountIndex, Balance, BlockNumber, Hash, Index, Moment};
+        #[allow(unused_imports)]
+        use substrate_rpc_client::extrinsic::xt_primitives::GenericAddress;
+
+        pub const MODULE_INDEX: u8 = 0;
+
+        pub fn sudo<Call0: Encode>(proposal: Call0) -> ([u8; 2], Call0) {
+            ([MODULE_INDEX, 0], proposal)
+        }
+    }
+    pub mod storage {
+        #[allow(unused_imports)]
+        use super::super::*;
+        #[allow(unused_imports)]
+        use substrate_rpc_client::extrinsic::codec::{Compact, Decode, Encode, Error, Input};
+        #[allow(unused_imports)]
+        use substrate_rpc_client::extrinsic::node_primitives::{AccountId, AccountIndex, Balance, BlockNumber, Hash, Index, Moment};
+        #[allow(unused_imports)]
+        use substrate_rpc_client::extrinsic::xt_primitives::GenericAddress;
+        use substrate_rpc_client::{Api, WsResult};
+        use substrate_rpc_client::extrinsic::node_primitives::Signature;
+        use substrate_rpc_client::extrinsic::primitives::crypto::Pair;
+
+        pub fn key<P>(api: &Api<P>, at: Option<Hash>) -> WsResult<Option<AccountId>>
+        where
+            P: Pair,
+            Signature: From<P::Signature>,
+        {
+            api.get_storage_value("Sudo", "Key", at)
+        }
+    }
+}
+
+#[allow(dead_code, non_camel_case_types)]
+pub mod balances {
+    pub mod calls {
+        #[allow(unused_imports)]
+        use super::super::*;
+        #[allow(unused_imports)]
+        use substrate_rpc_client::extrinsic::codec::{Compact, Decode, Encode, Error, Input};
+        #[allow(unused_imports)]
+        use substrate_rpc_client::extrinsic::node_primitives::{AccountId, AccountIndex, Balance, BlockNumber, Hash, Index, Moment};
+        #[allow(unused_imports)]
+        use substrate_rpc_client::extrinsic::xt_primitives::GenericAddress;
+
+        pub const MODULE_INDEX: u8 = 1;
+
+        pub fn transfer(dest: GenericAddress, value: Compact<Balance>) -> ([u8; 2], GenericAddress, Compact<Balance>) {
+            ([MODULE_INDEX, 0], dest, value)
+        }
+    }
+    pub mod events {
+        #[allow(unused_imports)]
+        use super::super::*;
+        #[allow(unused_imports)]
+        use substrate_rpc_client::extrinsic::codec::{Compact, Decode, Encode, Error, Input};
+        #[allow(unused_imports)]
+        use substrate_rpc_client::extrinsic::node_primitives::{AccountId, AccountIndex, Balance, BlockNumber, Hash, Index, Moment};
+        #[allow(unused_imports)]
+        use substrate_rpc_client::extrinsic::xt_primitives::GenericAddress;
+
+        pub const MODULE_INDEX: u8 = 0;
+
+        #[derive(Clone, Debug)]
+        pub enum Event {
+            NewAccount(AccountId, Balance),
+            Dummy,
+        }
+
+        impl Decode for Event {
+            fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+                match input.read_byte()? {
+                    0 => Ok(Event::NewAccount(Decode::decode(input)?, Decode::decode(input)?)),
+                    1 => Ok(Event::Dummy),
+                    _ => Err("unknown event index".into()),
+                }
+            }
+        }
+    }
+    pub mod storage {
+        #[allow(unused_imports)]
+        use super::super::*;
+        #[allow(unused_imports)]
+        use substrate_rpc_client::extrinsic::codec::{Compact, Decode, Encode, Error, Input};
+        #[allow(unused_imports)]
+        use substrate_rpc_client::extrinsic::node_primitives::{AccountId, AccountIndex, Balance, BlockNumber, Hash, Index, Moment};
+        #[allow(unused_imports)]
+        use substrate_rpc_client::extrinsic::xt_primitives::GenericAddress;
+        use substrate_rpc_client::{Api, WsResult};
+        use substrate_rpc_client::extrinsic::node_primitives::Signature;
+        use substrate_rpc_client::extrinsic::primitives::crypto::Pair;
+
+        pub fn free_balance<P>(api: &Api<P>, key1: &AccountId, at: Option<Hash>) -> WsResult<Option<Balance>>
+        where
+            P: Pair,
+            Signature: From<P::Signature>,
+        {
+            api.get_storage_map("Balances", "FreeBalance", key1, at)
+        }
+
+        pub fn reserved<P>(api: &Api<P>, key1: &AccountId, key2: &BlockNumber, at: Option<Hash>) -> WsResult<Option<Balance>>
+        where
+            P: Pair,
+            Signature: From<P::Signature>,
+        {
+            api.get_storage_double_map("Balances", "Reserved", key1, key2, at)
+        }
+    }
+}
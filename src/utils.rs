@@ -16,50 +16,153 @@
 
 use hex::FromHexError;
 use primitive_types::U256;
-use primitives::{blake2_256, H256 as Hash, twox_128};
-
-fn storage_key_hash_vec(module: &str, storage_key_name: &str, param: Option<Vec<u8>>) -> Vec<u8> {
-    let mut key = [module, storage_key_name].join(" ").as_bytes().to_vec();
-    match param {
-        Some(par) => {
-            key.extend(&par);
-            blake2_256(&key).to_vec()
+use primitives::{blake2_128, blake2_256, H256 as Hash, twox_128, twox_256, twox_64};
+use serde::{Deserialize, Serialize};
+
+/// Hashers a storage map may declare for its keys.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum StorageHasher {
+    Blake2_128,
+    Blake2_256,
+    Blake2_128Concat,
+    Twox128,
+    Twox256,
+    Twox64Concat,
+    Identity,
+}
+
+impl StorageHasher {
+    pub fn hash(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            StorageHasher::Blake2_128 => blake2_128(data).to_vec(),
+            StorageHasher::Blake2_256 => blake2_256(data).to_vec(),
+            StorageHasher::Blake2_128Concat => [&blake2_128(data)[..], data].concat(),
+            StorageHasher::Twox128 => twox_128(data).to_vec(),
+            StorageHasher::Twox256 => twox_256(data).to_vec(),
+            StorageHasher::Twox64Concat => [&twox_64(data)[..], data].concat(),
+            StorageHasher::Identity => data.to_vec(),
+        }
+    }
+
+    /// Recovers the encoded key from its hashed form, which is only possible for the
+    /// concat hashers and identity.
+    pub fn reverse<'a>(&self, hashed: &'a [u8]) -> Option<&'a [u8]> {
+        match self {
+            StorageHasher::Blake2_128Concat if hashed.len() >= 16 => Some(&hashed[16..]),
+            StorageHasher::Twox64Concat if hashed.len() >= 8 => Some(&hashed[8..]),
+            StorageHasher::Identity => Some(hashed),
+            _ => None,
+        }
+    }
+}
+
+/// How storage keys are laid out.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum StorageLayout {
+    /// `twox128("Module Item")` for plain values, `hasher("Module Item" ++ key)` for maps and
+    /// `hasher("Module Item" ++ key1) ++ key2_hasher(key2)` for double maps.
+    Legacy,
+    /// `twox128(module) ++ twox128(item)`, followed by `hasher(key)` for each map key.
+    Prefixed,
+}
+
+impl Default for StorageLayout {
+    fn default() -> Self {
+        StorageLayout::Legacy
+    }
+}
+
+/// Builds a storage key. `keys` holds the encoded map keys together with their hashers:
+/// none for plain values, one for maps and two for double maps.
+pub fn storage_key(layout: StorageLayout, module: &str, storage_key_name: &str, keys: &[(StorageHasher, Vec<u8>)]) -> Vec<u8> {
+    match layout {
+        StorageLayout::Legacy => {
+            let mut key = [module, storage_key_name].join(" ").as_bytes().to_vec();
+            match keys.split_first() {
+                Some(((hasher, first), rest)) => {
+                    key.extend(first);
+                    let mut key = hasher.hash(&key);
+                    for (hasher, k) in rest {
+                        key.extend(hasher.hash(k));
+                    }
+                    key
+                },
+                None => twox_128(&key).to_vec(),
+            }
         },
-        _ => {
-            twox_128(&key).to_vec()
+        StorageLayout::Prefixed => {
+            let mut key = twox_128(module.as_bytes()).to_vec();
+            key.extend(&twox_128(storage_key_name.as_bytes()));
+            for (hasher, k) in keys {
+                key.extend(hasher.hash(k));
+            }
+            key
         },
     }
 }
 
-pub fn storage_key_hash(module: &str, storage_key_name: &str, param: Option<Vec<u8>>) -> String {
-    let mut keyhash_str = hex::encode(
-        storage_key_hash_vec(module, storage_key_name, param));
+/// The common prefix of all keys of a map, or of a double map under the given first key(s).
+/// Maps in the legacy layout hash the key together with the item name and have no
+/// common prefix, so `None` is returned for them.
+pub fn storage_key_prefix(layout: StorageLayout, module: &str, storage_key_name: &str, keys: &[(StorageHasher, Vec<u8>)]) -> Option<Vec<u8>> {
+    match layout {
+        StorageLayout::Legacy if keys.is_empty() => None,
+        _ => Some(storage_key(layout, module, storage_key_name, keys)),
+    }
+}
+
+pub fn storage_key_hex(layout: StorageLayout, module: &str, storage_key_name: &str, keys: &[(StorageHasher, Vec<u8>)]) -> String {
+    let mut keyhash_str = hex::encode(storage_key(layout, module, storage_key_name, keys));
     keyhash_str.insert_str(0, "0x");
     keyhash_str
 }
 
+/// Storage key in the legacy layout, hashing a map key with blake2_256.
+pub fn storage_key_hash(module: &str, storage_key_name: &str, param: Option<Vec<u8>>) -> String {
+    let keys = param.into_iter().map(|p| (StorageHasher::Blake2_256, p)).collect::<Vec<_>>();
+    storage_key_hex(StorageLayout::Legacy, module, storage_key_name, &keys)
+}
+
+/// Storage key in the legacy layout, hashing both map keys with blake2_256.
 pub fn storage_key_hash_double_map(module: &str, storage_key_name: &str, first: Vec<u8>, second: Vec<u8>) -> String {
-    let mut keyhash = storage_key_hash_vec(module, storage_key_name, Some(first));
-    keyhash.extend(&blake2_256(&second).to_vec());
-    let mut keyhash_str = hex::encode(keyhash);
-    keyhash_str.insert_str(0, "0x");
-    keyhash_str
+    let keys = [(StorageHasher::Blake2_256, first), (StorageHasher::Blake2_256, second)];
+    storage_key_hex(StorageLayout::Legacy, module, storage_key_name, &keys)
 }
 
+/// Prefix of the keys under which the roots of default child tries are stored.
+pub const CHILD_STORAGE_KEY_PREFIX: &[u8] = b":child_storage:default:";
+
+/// The key of the child trie with the given identifier, e.g. a contract's trie id.
+pub fn child_storage_key(child_id: &[u8]) -> Vec<u8> {
+    [CHILD_STORAGE_KEY_PREFIX, child_id].concat()
+}
+
+pub fn child_storage_key_hex(child_id: &[u8]) -> String {
+    format!("0x{}", hex::encode(child_storage_key(child_id)))
+}
+
+/// Decodes a hex string, optionally quoted and `0x` prefixed. The json `null` the node returns
+/// for absent keys is rejected, use `hexstr_to_opt_vec` where a value may be absent.
 pub fn hexstr_to_vec(hexstr: String) -> Result<Vec<u8>, FromHexError> {
     let hexstr = hexstr
         .trim_matches('\"')
         .to_string()
         .trim_start_matches("0x")
         .to_string();
+    hex::decode(&hexstr)
+}
+
+/// Like `hexstr_to_vec`, but maps the json `null` the node returns for absent keys to `None`.
+pub fn hexstr_to_opt_vec(hexstr: String) -> Result<Option<Vec<u8>>, FromHexError> {
     match hexstr.as_str() {
-        "null" => Ok(vec!(0u8)),
-        _ => hex::decode(&hexstr),
+        "null" => Ok(None),
+        _ => hexstr_to_vec(hexstr).map(Some),
     }
 }
 
+/// Absent values (`null`) read as 0, the default of numbers in storage.
 pub fn hexstr_to_u64(hexstr: String) -> Result<u64, FromHexError> {
-    let unhex = hexstr_to_vec(hexstr);
+    let unhex = hexstr_to_opt_vec(hexstr).map(|v| v.unwrap_or_else(|| vec![0u8]));
     match unhex {
         Ok(vec) => {
             match vec.len() {
@@ -79,8 +182,9 @@ pub fn hexstr_to_u64(hexstr: String) -> Result<u64, FromHexError> {
     }
 }
 
+/// Absent values (`null`) read as 0, the default of numbers in storage.
 pub fn hexstr_to_u256(hexstr: String) -> Result<U256, FromHexError> {
-    let unhex = hexstr_to_vec(hexstr);
+    let unhex = hexstr_to_opt_vec(hexstr).map(|v| v.unwrap_or_else(|| vec![0u8]));
     match unhex {
         Ok(vec) => {
             match vec.len() {
@@ -119,13 +223,105 @@ mod tests {
     // Note this useful idiom: importing names from outer (for mod tests) scope.
     use super::*;
 
+    const ALICE: &str = "d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+    const BOB: &str = "8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48";
+
+    #[test]
+    fn test_storage_hashers() {
+        let one = 1u32.to_le_bytes().to_vec();
+        assert_eq!(hex::encode(StorageHasher::Twox64Concat.hash(&one)), "5153cb1f00942ff401000000");
+        assert_eq!(hex::encode(StorageHasher::Blake2_128Concat.hash(&one)), "d82c12285b5d4551f88e8f6e7eb52b8101000000");
+        assert_eq!(hex::encode(StorageHasher::Twox256.hash(b"abc")), "990977adf52cbc440889329981caa9bef7da5770b2b8a05303b75d95360dd62b");
+        assert_eq!(StorageHasher::Identity.hash(&one), one);
+    }
+
+    #[test]
+    fn test_legacy_storage_keys() {
+        let alice = hex::decode(ALICE).unwrap();
+        let bob = hex::decode(BOB).unwrap();
+        assert_eq!(storage_key_hash("Sudo", "Key", None), "0x50a63a871aced22e88ee6466fe5aa5d9");
+        assert_eq!(
+            storage_key_hash("Balances", "FreeBalance", Some(alice.clone())),
+            "0x7f864e18e3dd8b58386310d2fe0919eef27c6e558564b7f67f22d99d20f587bb"
+        );
+        assert_eq!(
+            storage_key_hash_double_map("Mod", "DM", alice, bob),
+            "0x21f4907dea9e2aa43cd1d43c390681986c7558dc7b965ed5bf63c57867b24c88\
+             94772f97f5f6b539aac74e798bc395119f39603402d0c85bc9eda5dfc5ae2160"
+        );
+    }
+
+    #[test]
+    fn test_prefixed_storage_keys() {
+        let alice = hex::decode(ALICE).unwrap();
+        let bob = hex::decode(BOB).unwrap();
+        assert_eq!(
+            storage_key_hex(StorageLayout::Prefixed, "Timestamp", "Now", &[]),
+            "0xf0c365c3cf59d671eb72da0e7a4113c49f1f0515f462cdcf84e0f1d6045dfcbb"
+        );
+        assert_eq!(
+            storage_key_hex(StorageLayout::Prefixed, "System", "Account", &[(StorageHasher::Blake2_128Concat, alice.clone())]),
+            format!("0x26aa394eea5630e07c48ae0c9558cef7b99d880ec681799c0cf30e8886371da9de1e86a9a8c739864cf3cc5ec2bea59f{}", ALICE)
+        );
+        assert_eq!(
+            storage_key_hex(
+                StorageLayout::Prefixed,
+                "Mod",
+                "DM",
+                &[(StorageHasher::Twox64Concat, alice), (StorageHasher::Blake2_128Concat, bob)]
+            ),
+            format!(
+                "0x4d749edf9abd88a07bfe6bc175cfadf08cdd837d9c01d5262bdd4bbd3ab76cae518366b5b1bc7c99{}4f9aea1afa791265fae359272badc1cf{}",
+                ALICE, BOB
+            )
+        );
+    }
+
+    #[test]
+    fn test_storage_hasher_reverse() {
+        let key = 7u64.to_le_bytes().to_vec();
+        for hasher in &[StorageHasher::Blake2_128Concat, StorageHasher::Twox64Concat, StorageHasher::Identity] {
+            assert_eq!(hasher.reverse(&hasher.hash(&key)), Some(&key[..]));
+        }
+        assert_eq!(StorageHasher::Blake2_256.reverse(&StorageHasher::Blake2_256.hash(&key)), None);
+        assert_eq!(StorageHasher::Twox64Concat.reverse(&[1, 2]), None);
+    }
+
+    #[test]
+    fn test_storage_key_prefix() {
+        let alice = hex::decode(ALICE).unwrap();
+        assert_eq!(storage_key_prefix(StorageLayout::Legacy, "Balances", "FreeBalance", &[]), None);
+        assert_eq!(
+            storage_key_prefix(StorageLayout::Prefixed, "Balances", "FreeBalance", &[]),
+            Some(storage_key(StorageLayout::Prefixed, "Balances", "FreeBalance", &[]))
+        );
+        let first = [(StorageHasher::Blake2_256, alice)];
+        let prefix = storage_key_prefix(StorageLayout::Legacy, "Staking", "Nominators", &first).unwrap();
+        assert_eq!(prefix, storage_key(StorageLayout::Legacy, "Staking", "Nominators", &first));
+        assert_eq!(prefix.len(), 32);
+    }
+
+    #[test]
+    fn test_child_storage_key() {
+        assert_eq!(child_storage_key(&[1, 2]), b":child_storage:default:\x01\x02".to_vec());
+        assert_eq!(child_storage_key_hex(b"id"), "0x3a6368696c645f73746f726167653a64656661756c743a6964");
+    }
+
     #[test]
     fn test_hextstr_to_vec() {
         assert_eq!(hexstr_to_vec("0x01020a".to_string()), Ok(vec!(1,2,10)));
-        assert_eq!(hexstr_to_vec("null".to_string()), Ok(vec!(0u8)));
+        assert_eq!(hexstr_to_vec("null".to_string()), Err(hex::FromHexError::InvalidHexCharacter{c: 'n', index:0}));
         assert_eq!(hexstr_to_vec("0x0q".to_string()), Err(hex::FromHexError::InvalidHexCharacter{c: 'q', index:1}));
     }
 
+    #[test]
+    fn test_hextstr_to_opt_vec() {
+        assert_eq!(hexstr_to_opt_vec("\"0x01020a\"".to_string()), Ok(Some(vec!(1,2,10))));
+        assert_eq!(hexstr_to_opt_vec("\"0x00\"".to_string()), Ok(Some(vec!(0u8))));
+        assert_eq!(hexstr_to_opt_vec("null".to_string()), Ok(None));
+        assert_eq!(hexstr_to_opt_vec("0x0q".to_string()), Err(hex::FromHexError::InvalidHexCharacter{c: 'q', index:1}));
+    }
+
     #[test]
     fn test_hextstr_to_u64() {
         assert_eq!(hexstr_to_u64("0x0100000000000000".to_string()), Ok(1u64));
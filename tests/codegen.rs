@@ -0,0 +1,42 @@
+// Copyright 2019 Liebi Technologies.
+// This file is part of Bifrost.
+
+// Bifrost is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Bifrost is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Bifrost.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Compiles the code generated from the metadata of the `codegen` unit tests, so the
+//! generated accessors keep matching the signatures of `Api`.
+
+mod runtime {
+    include!("../src/test_data/codegen_runtime.rs");
+}
+
+use runtime::balances::{calls, events};
+use substrate_rpc_client::extrinsic::codec::{Compact, Decode};
+use substrate_rpc_client::extrinsic::xt_primitives::GenericAddress;
+
+#[test]
+fn test_generated_code() {
+    let dest = GenericAddress::from(substrate_rpc_client::extrinsic::node_primitives::AccountId::from([1u8; 32]));
+    let (index, _, value) = calls::transfer(dest, Compact(42));
+    assert_eq!(index, [calls::MODULE_INDEX, 0]);
+    assert_eq!(value.0, 42);
+
+    match events::Event::decode(&mut &[1u8][..]) {
+        Ok(events::Event::Dummy) => {}
+        other => panic!("unexpected event {:?}", other),
+    }
+
+    // the storage accessors only need to type check against `Api`
+    let _ = runtime::balances::storage::reserved::<substrate_rpc_client::extrinsic::primitives::sr25519::Pair>;
+}